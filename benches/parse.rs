@@ -0,0 +1,150 @@
+//! Compares the streaming feed parser against reading the same feed with the `rss` crate, which it replaced, on a feed
+//! the size of a full page of Jackett results.  The `rss` side also pulls the attrs out of each item's extension map and
+//! makes the second pass for the elements `rss` drops, as the crate used to, so that both sides do the same work.
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use torznab::ParseOptions;
+
+fn feed(items: usize) -> String {
+	let mut feed = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+  <channel>
+    <atom:link href="http://127.0.0.1:9117/" rel="self" type="application/rss+xml" />
+    <title>Jackett</title>
+    <description>Jackett aggregate indexer</description>
+    <link>http://127.0.0.1:9117/</link>
+    <language>en-US</language>
+    <category>search</category>
+"#);
+	for i in 0..items {
+		feed.push_str(&format!(r#"    <item>
+      <title>Some.Release.{i}.2021.1080p.WEB-DL.DDP5.1.H.264-GRP</title>
+      <guid>http://127.0.0.1:9117/details/{i}</guid>
+      <jackettindexer id="rarbg">RARBG</jackettindexer>
+      <type>public</type>
+      <comments>http://127.0.0.1:9117/details/{i}</comments>
+      <pubDate>Sat, 03 Apr 2021 05:29:49 -0400</pubDate>
+      <size>{size}</size>
+      <description />
+      <link>http://127.0.0.1:9117/dl/rarbg/?jackett_apikey=key&amp;path={i}&amp;file=Some.Release</link>
+      <category>2000</category>
+      <category>100040</category>
+      <enclosure url="http://127.0.0.1:9117/dl/rarbg/?jackett_apikey=key&amp;path={i}&amp;file=Some.Release" length="{size}" type="application/x-bittorrent" />
+      <torznab:attr name="category" value="2000" />
+      <torznab:attr name="category" value="100040" />
+      <torznab:attr name="seeders" value="{seeders}" />
+      <torznab:attr name="peers" value="{peers}" />
+      <torznab:attr name="infohash" value="cea91d952f2c94777944059284ed7422f6d3c7c8" />
+      <torznab:attr name="magneturl" value="magnet:?xt=urn:btih:cea91d952f2c94777944059284ed7422f6d3c7c8&amp;dn=Some.Release" />
+      <torznab:attr name="minimumratio" value="1" />
+      <torznab:attr name="minimumseedtime" value="172800" />
+      <torznab:attr name="downloadvolumefactor" value="0" />
+      <torznab:attr name="uploadvolumefactor" value="1" />
+    </item>
+"#, i = i, size = 1_000_000_000 + i, seeders = i % 100, peers = i % 100 + 5));
+	}
+	feed.push_str("  </channel>\n</rss>\n");
+	feed
+}
+
+/// What the crate used to take from each `rss` item before parsing it into a result.  Nothing reads it back; the
+/// benchmark only needs it built.
+#[allow(dead_code)]
+#[derive(Default)]
+struct RssItem {
+	title: Option<String>,
+	guid: Option<String>,
+	link: Option<String>,
+	pub_date: Option<String>,
+	categories: Vec<String>,
+	enclosure: Option<(String, String, String)>,
+	attrs: Vec<(&'static str, String, String)>,
+	extras: Extras
+}
+
+/// The `<size>` and `<jackettindexer>` of an item, which `rss` doesn't keep.
+#[allow(dead_code)]
+#[derive(Default)]
+struct Extras {
+	size: Option<String>,
+	indexer: Option<(Option<String>, String)>
+}
+
+fn read_with_rss(feed: &[u8]) -> Vec<RssItem> {
+	let channel = rss::Channel::read_from(feed).unwrap();
+	let mut extras = read_extras(feed).into_iter();
+	channel.items.into_iter().map(|item| {
+		let mut attrs = Vec::new();
+		for prefix in ["torznab", "newznab"] {
+			for extension in item.extensions().get(prefix).and_then(|extensions| extensions.get("attr")).into_iter().flatten() {
+				if let (Some(name), Some(value)) = (extension.attrs().get("name"), extension.attrs().get("value")) {
+					attrs.push((prefix, name.clone(), value.clone()));
+				}
+			}
+		}
+		RssItem{
+			enclosure: item.enclosure().map(|enclosure| (enclosure.url().to_string(), enclosure.length().to_string(), enclosure.mime_type().to_string())),
+			categories: item.categories().iter().map(|category| category.name().to_string()).collect(),
+			guid: item.guid.map(|guid| guid.value),
+			title: item.title,
+			link: item.link,
+			pub_date: item.pub_date,
+			attrs,
+			extras: extras.next().unwrap_or_default()
+		}
+	}).collect()
+}
+
+enum Field {
+	Size,
+	Indexer(Option<String>)
+}
+
+fn read_extras(feed: &[u8]) -> Vec<Extras> {
+	let mut reader = Reader::from_reader(feed);
+	let mut buf = Vec::new();
+	let mut items = Vec::new();
+	let mut field = None;
+	loop {
+		match reader.read_event_into(&mut buf).unwrap() {
+			Event::Start(element) => match element.name().as_ref() {
+				b"item" => items.push(Extras::default()),
+				b"size" => field = Some(Field::Size),
+				b"jackettindexer" => field = Some(Field::Indexer(element.try_get_attribute("id").unwrap().map(|id| String::from_utf8(id.value.into_owned()).unwrap()))),
+				_ => {}
+			},
+			Event::Text(text) => if let (Some(field), Some(item)) = (field.take(), items.last_mut()) {
+				let text = text.decode().unwrap().into_owned();
+				match field {
+					Field::Size => item.size = Some(text),
+					Field::Indexer(id) => item.indexer = Some((id, text))
+				}
+			},
+			Event::End(_) => field = None,
+			Event::Eof => break,
+			_ => {}
+		}
+		buf.clear();
+	}
+	items
+}
+
+fn parse(c: &mut Criterion) {
+	let mut group = c.benchmark_group("parse_feed");
+	for items in [100, 1000] {
+		let feed = feed(items);
+		group.throughput(Throughput::Bytes(feed.len() as u64));
+		group.bench_with_input(BenchmarkId::new("streaming", items), feed.as_bytes(), |b, feed| b.iter(|| ParseOptions::default().parse_feed(feed).unwrap()));
+		group.bench_with_input(BenchmarkId::new("rss", items), feed.as_bytes(), |b, feed| b.iter(|| read_with_rss(feed)));
+	}
+	group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,198 @@
+//! A synchronous wrapper around [`crate::Client`], for callers without an async runtime of their own.
+//!
+//! Each client drives its requests on a private single-threaded tokio runtime.  As with `reqwest::blocking`, its
+//! methods must not be called from within an async context; they panic if they are.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use smartcow::SmartCow;
+use tokio::runtime::Runtime;
+
+use crate::auth::Refresh;
+use crate::Caps;
+use crate::CookieJar;
+use crate::Credentials;
+use crate::Download;
+use crate::Encoding;
+use crate::Error;
+use crate::Feed;
+use crate::HealthPolicy;
+use crate::HealthStatus;
+use crate::Limits;
+use crate::ParseMode;
+use crate::Protocol;
+use crate::Query;
+use crate::SearchResponse;
+use crate::SearchResult;
+use crate::TorznabCategory;
+
+#[derive(Clone)]
+/// A blocking Torznab client; see [`crate::Client`] for details of each method.
+pub struct Client {
+	inner: crate::Client,
+	runtime: Arc<Runtime>
+}
+
+impl Client {
+	#[cfg(feature = "reqwest")]
+	pub fn new(base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		Self::from_async(crate::Client::new(base_url, apikey)?)
+	}
+
+	#[cfg(feature = "reqwest")]
+	pub fn newznab(base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		Self::from_async(crate::Client::newznab(base_url, apikey)?)
+	}
+
+	#[cfg(all(unix, feature = "unix-socket"))]
+	pub fn with_unix_socket(socket: impl Into<std::path::PathBuf>, base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		Self::from_async(crate::Client::with_unix_socket(socket, base_url, apikey)?)
+	}
+
+	/// Wraps an async client, e.g. one from [`Jackett`](crate::Jackett) or [`Prowlarr`](crate::Prowlarr).
+	pub fn from_async(inner: crate::Client) -> Result<Self, Error> {
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+		Ok(Self{inner, runtime: Arc::new(runtime)})
+	}
+
+	pub fn into_async(self) -> crate::Client {
+		self.inner
+	}
+
+	pub fn with_parse_mode(mut self, parse_mode: ParseMode) -> Self {
+		self.inner = self.inner.with_parse_mode(parse_mode);
+		self
+	}
+
+	pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+		self.inner = self.inner.with_protocol(protocol);
+		self
+	}
+
+	pub fn with_limits(mut self, limits: Limits) -> Self {
+		self.inner = self.inner.with_limits(limits);
+		self
+	}
+
+	pub fn with_title_repair(mut self, repair_titles: bool) -> Self {
+		self.inner = self.inner.with_title_repair(repair_titles);
+		self
+	}
+
+	pub fn with_encoding(mut self, encoding: &'static Encoding) -> Self {
+		self.inner = self.inner.with_encoding(encoding);
+		self
+	}
+
+	pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+		self.inner = self.inner.with_credentials(credentials);
+		self
+	}
+
+	pub fn with_cookie_jar(mut self, jar: CookieJar) -> Self {
+		self.inner = self.inner.with_cookie_jar(jar);
+		self
+	}
+
+	pub fn with_credential_refresh(mut self, refresh: impl Refresh + 'static) -> Self {
+		self.inner = self.inner.with_credential_refresh(refresh);
+		self
+	}
+
+	pub fn with_name(mut self, name: impl ToString) -> Self {
+		self.inner = self.inner.with_name(name);
+		self
+	}
+
+	pub fn with_health_policy(mut self, policy: HealthPolicy) -> Self {
+		self.inner = self.inner.with_health_policy(policy);
+		self
+	}
+
+	pub fn with_caps(mut self, caps: Caps) -> Self {
+		self.inner = self.inner.with_caps(caps);
+		self
+	}
+
+	pub fn health(&self) -> HealthStatus {
+		self.inner.health()
+	}
+
+	pub fn reset_health(&self) {
+		self.inner.reset_health()
+	}
+
+	pub fn get(&self, t: TorznabCategory, qparams: Vec<(&str, SmartCow<'_>)>) -> Result<Bytes, Error> {
+		self.runtime.block_on(self.inner.get(t, qparams))
+	}
+
+	pub fn caps(&self) -> Result<Caps, Error> {
+		self.runtime.block_on(self.inner.caps())
+	}
+
+	pub fn cached_caps(&self) -> Result<Arc<Caps>, Error> {
+		self.runtime.block_on(self.inner.cached_caps())
+	}
+
+	pub fn invalidate_caps(&self) {
+		self.inner.invalidate_caps()
+	}
+
+	pub fn get_items(&self, t: TorznabCategory, qparams: Vec<(&str, SmartCow<'_>)>) -> Result<Feed, Error> {
+		self.runtime.block_on(self.inner.get_items(t, qparams))
+	}
+
+	pub fn search(&self, t: TorznabCategory, query: &Query) -> Result<Vec<Result<SearchResult, Error>>, Error> {
+		self.runtime.block_on(self.inner.search(t, query))
+	}
+
+	pub fn negotiated_search(&self, t: TorznabCategory, query: &Query) -> Result<SearchResponse, Error> {
+		self.runtime.block_on(self.inner.negotiated_search(t, query))
+	}
+
+	pub fn tvsearch(&self, q: Option<&str>) -> Result<Vec<Result<SearchResult, Error>>, Error> {
+		self.runtime.block_on(self.inner.tvsearch(q))
+	}
+
+	pub fn moviesearch(&self, q: Option<&str>) -> Result<Vec<Result<SearchResult, Error>>, Error> {
+		self.runtime.block_on(self.inner.moviesearch(q))
+	}
+
+	pub fn audiosearch(&self, q: Option<&str>) -> Result<Vec<Result<SearchResult, Error>>, Error> {
+		self.runtime.block_on(self.inner.audiosearch(q))
+	}
+
+	pub fn download(&self, result: &SearchResult) -> Result<Download, Error> {
+		self.runtime.block_on(self.inner.download(result))
+	}
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+	use super::*;
+	use crate::test_server::serve;
+	use crate::test_server::Response;
+
+	#[test]
+	fn search_and_download() {
+		let server = Runtime::new().unwrap();
+		let base_url = server.block_on(serve(|request| match (request.path(), request.query("t").as_deref()) {
+			("/api", Some("caps")) => Response::new(200, r#"<caps><searching><search available="yes" supportedParams="q" /></searching></caps>"#),
+			("/api", _) => Response::new(200, r#"<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed"><channel><title>t</title>
+				<item><title>Release</title><guid>1</guid><link>http://localhost/release.torrent</link><enclosure url="http://localhost/release.torrent" length="4" type="application/x-bittorrent" /></item>
+			</channel></rss>"#),
+			("/release.torrent", _) => Response::new(200, "d4:infoe"),
+			_ => Response::new(404, "")
+		}));
+
+		let client = Client::new(&base_url, "key").unwrap();
+		assert!(client.caps().unwrap().is_available(crate::SearchMode::Search));
+		let feed = client.get_items(TorznabCategory::Movies2000, vec![("t", SmartCow::Borrowed("search"))]).unwrap();
+		assert_eq!(feed.results.len(), 1);
+		let mut result = client.search(TorznabCategory::Movies2000, &Query::new().q("release")).unwrap().remove(0).unwrap();
+		assert_eq!(result.name, "Release");
+		result.link = format!("{}/release.torrent", base_url);
+		assert!(matches!(client.download(&result).unwrap(), Download::Torrent(bytes) if bytes.as_ref() == b"d4:infoe"));
+	}
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::xml::attr;
+use crate::xml::skip;
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A search function (`t=` value) an indexer may support.
+pub enum SearchMode {
+	Search,
+	TvSearch,
+	MovieSearch,
+	MusicSearch,
+	BookSearch
+}
+
+impl SearchMode {
+	/// The value of `t` for this mode.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Search => "search",
+			Self::TvSearch => "tvsearch",
+			Self::MovieSearch => "movie",
+			Self::MusicSearch => "music",
+			Self::BookSearch => "book"
+		}
+	}
+
+	/// Parameters the Newznab spec assumes when caps omit `supportedParams`.
+	fn default_params(self) -> &'static [&'static str] {
+		match self {
+			Self::Search => &["q"],
+			Self::TvSearch => &["q", "rid", "tvdbid", "season", "ep"],
+			Self::MovieSearch => &["q", "imdbid"],
+			Self::MusicSearch => &["q", "album", "artist", "label", "year", "genre"],
+			Self::BookSearch => &["q", "title", "author"]
+		}
+	}
+
+	fn from_caps_element(name: &[u8]) -> Option<Self> {
+		match name {
+			b"search" => Some(Self::Search),
+			b"tv-search" => Some(Self::TvSearch),
+			b"movie-search" => Some(Self::MovieSearch),
+			b"music-search" | b"audio-search" => Some(Self::MusicSearch),
+			b"book-search" => Some(Self::BookSearch),
+			_ => None
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchCap {
+	pub available: bool,
+	pub supported_params: Vec<String>
+}
+
+impl SearchCap {
+	/// Folds in another element for the same mode, such as `audio-search` alongside `music-search`:  the mode is
+	/// available if either says so, with the params of both.
+	fn merge(&mut self, other: SearchCap) {
+		self.available |= other.available;
+		for param in other.supported_params {
+			if(!self.supported_params.contains(&param)) {
+				self.supported_params.push(param);
+			}
+		}
+	}
+
+	pub fn supports(&self, param: &str) -> bool {
+		self.available && self.supported_params.iter().any(|supported| supported == param)
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CapsCategory {
+	pub id: u32,
+	pub name: String,
+	pub subcategories: Vec<CapsCategory>
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// An indexer's capabilities, as returned by `t=caps`.
+pub struct Caps {
+	pub server_title: Option<String>,
+	/// Default and maximum number of results per page.
+	pub limits: Option<(u32, u32)>,
+	pub searching: BTreeMap<SearchMode, SearchCap>,
+	pub categories: Vec<CapsCategory>
+}
+
+impl Caps {
+	pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+		let mut reader = Reader::from_reader(bytes);
+		reader.config_mut().expand_empty_elements = true;
+		let mut buf = Vec::new();
+		loop {
+			match reader.read_event_into(&mut buf)? {
+				Event::Start(element) if element.local_name().as_ref() == b"caps" => return read_caps(&mut reader),
+				Event::Start(element) if element.local_name().as_ref() == b"error" => return Err(indexer_error(&reader, &element)?),
+				Event::Eof => return Err(Error::MissingField("caps".to_string())),
+				_ => {}
+			}
+			buf.clear();
+		}
+	}
+
+	/// Whether `mode` is available at all.
+	pub fn is_available(&self, mode: SearchMode) -> bool {
+		self.searching.get(&mode).map(|cap| cap.available).unwrap_or(false)
+	}
+
+	/// Whether `mode` is available and accepts `param`.
+	pub fn supports(&self, mode: SearchMode, param: &str) -> bool {
+		self.searching.get(&mode).map(|cap| cap.supports(param)).unwrap_or(false)
+	}
+}
+
+/// Turns an `<error code="..." description="..." />` document into an [`Error::Indexer`].
+pub(crate) fn indexer_error<R: BufRead>(reader: &Reader<R>, element: &BytesStart<'_>) -> Result<Error, Error> {
+	Ok(Error::Indexer{
+		code: attr(reader, element, b"code")?.and_then(|code| code.parse().ok()),
+		description: attr(reader, element, b"description")?.unwrap_or_default()
+	})
+}
+
+fn read_category<R: BufRead>(reader: &Reader<R>, element: &BytesStart<'_>) -> Result<CapsCategory, Error> {
+	Ok(CapsCategory{
+		id: attr(reader, element, b"id")?.ok_or_else(|| Error::MissingField("id".to_string()))?.parse()?,
+		name: attr(reader, element, b"name")?.unwrap_or_default(),
+		subcategories: Vec::new()
+	})
+}
+
+/// Reads the contents of a `<caps>` element, up to and including its end tag.
+pub(crate) fn read_caps<R: BufRead>(reader: &mut Reader<R>) -> Result<Caps, Error> {
+	let mut caps = Caps::default();
+	let mut buf = Vec::new();
+	let mut depth = 0;
+	loop {
+		match reader.read_event_into(&mut buf)? {
+			Event::Start(element) => {
+				depth += 1;
+				let name = element.local_name();
+				match name.as_ref() {
+					b"server" => caps.server_title = attr(reader, &element, b"title")?,
+					b"limits" => {
+						let limit = |key| -> Result<Option<u32>, Error> {
+							Ok(attr(reader, &element, key)?.and_then(|v| v.parse().ok()))
+						};
+						let (default, max) = (limit(b"default")?, limit(b"max")?);
+						caps.limits = default.or(max).zip(max.or(default));
+					},
+					b"category" => caps.categories.push(read_category(reader, &element)?),
+					b"subcat" => {
+						let subcat = read_category(reader, &element)?;
+						if let Some(category) = caps.categories.last_mut() {
+							category.subcategories.push(subcat);
+						}
+					},
+					other => if let Some(mode) = SearchMode::from_caps_element(other) {
+						let cap = SearchCap{
+							available: attr(reader, &element, b"available")?.map(|v| v == "yes").unwrap_or(false),
+							supported_params: attr(reader, &element, b"supportedParams")?
+								.map(|params| params.split(',').map(|param| param.trim().to_string()).filter(|param| !param.is_empty()).collect())
+								.unwrap_or_else(|| mode.default_params().iter().map(|param| param.to_string()).collect())
+						};
+						caps.searching.entry(mode).or_default().merge(cap);
+					} else if(!matches!(other, b"searching" | b"categories")) {
+						skip(reader, &element)?;
+						depth -= 1;
+					}
+				}
+			},
+			Event::End(_) => {
+				if(depth == 0) {
+					break;
+				}
+				depth -= 1;
+			},
+			Event::Eof => return Err(Error::MissingField("</caps>".to_string())),
+			_ => {}
+		}
+		buf.clear();
+	}
+	Ok(caps)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse() {
+		let caps = Caps::parse(br#"<?xml version="1.0" encoding="UTF-8"?>
+			<caps>
+			  <server title="Jackett" />
+			  <limits default="100" max="100" />
+			  <searching>
+				<search available="yes" supportedParams="q" />
+				<tv-search available="yes" supportedParams="q,season,ep,imdbid" />
+				<movie-search available="yes" supportedParams="q" />
+				<music-search available="yes" supportedParams="q,artist" />
+				<audio-search available="no" supportedParams="q,album" />
+				<book-search available="yes" />
+			  </searching>
+			  <categories>
+				<category id="2000" name="Movies">
+				  <subcat id="2030" name="Movies/SD" />
+				  <subcat id="2040" name="Movies/HD" />
+				</category>
+				<category id="5000" name="TV" />
+			  </categories>
+			</caps>
+		"#).unwrap();
+		assert_eq!(caps.server_title.as_deref(), Some("Jackett"));
+		assert_eq!(caps.limits, Some((100, 100)));
+		assert!(caps.supports(SearchMode::TvSearch, "imdbid"));
+		assert!(!caps.supports(SearchMode::MovieSearch, "imdbid"));
+		assert!(caps.supports(SearchMode::MusicSearch, "artist") && caps.supports(SearchMode::MusicSearch, "album"));
+		assert_eq!(caps.searching[&SearchMode::MusicSearch].supported_params, ["q", "artist", "album"]);
+		assert!(caps.supports(SearchMode::BookSearch, "author"));
+		assert_eq!(caps.categories.len(), 2);
+		assert_eq!(caps.categories[0].subcategories[1].name, "Movies/HD");
+
+		let caps = Caps::parse(br#"<caps><searching>
+			<music-search available="no" supportedParams="q" />
+			<audio-search available="no" supportedParams="q,artist" />
+		</searching></caps>"#).unwrap();
+		assert!(!caps.is_available(SearchMode::MusicSearch));
+		assert_eq!(caps.searching[&SearchMode::MusicSearch].supported_params, ["q", "artist"]);
+	}
+}
@@ -0,0 +1,128 @@
+//! Transcoding of feeds in legacy charsets to UTF-8, and repair of text that was double-encoded on its way through
+//! an aggregator.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use bytes::Bytes;
+use encoding_rs::Encoding;
+use encoding_rs::UTF_8;
+use encoding_rs::WINDOWS_1251;
+use encoding_rs::WINDOWS_1252;
+
+/// The `charset` parameter of a `Content-Type` header.
+fn content_type_charset(content_type: &str) -> Option<&'static Encoding> {
+	content_type.split(';').skip(1)
+		.filter_map(|param| param.split_once('='))
+		.find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+		.and_then(|(_, value)| Encoding::for_label(value.trim().trim_matches('"').as_bytes()))
+}
+
+/// Where the value of the `encoding` pseudo-attribute of `document`'s XML declaration is.
+fn declared_span(document: &[u8]) -> Option<Range<usize>> {
+	if(!document.starts_with(b"<?xml")) {
+		return None;
+	}
+	let end = document.windows(2).position(|w| w == b"?>")?;
+	let declaration = &document[..end];
+	let start = declaration.windows(8).position(|w| w == b"encoding")? + 8;
+	let rest = &declaration[start..];
+	let equals = rest.iter().position(|b| !b.is_ascii_whitespace())?;
+	if(rest[equals] != b'=') {
+		return None;
+	}
+	let quote_at = equals + 1 + rest[equals + 1..].iter().position(|b| !b.is_ascii_whitespace())?;
+	let quote = rest[quote_at];
+	if(quote != b'"' && quote != b'\'') {
+		return None;
+	}
+	let len = rest[quote_at + 1..].iter().position(|b| *b == quote)?;
+	let value_start = start + quote_at + 1;
+	Some(value_start..value_start + len)
+}
+
+/// Works out what `body` is encoded in:  `forced` if given, then a byte order mark, then the `charset` of
+/// `content_type`, then the XML declaration.  A declaration is ignored for a body that's valid UTF-8, since
+/// indexers that re-encode feeds often leave the original declaration in place.
+pub(crate) fn detect(body: &[u8], content_type: Option<&str>, forced: Option<&'static Encoding>) -> &'static Encoding {
+	if let Some(encoding) = forced {
+		return encoding;
+	}
+	if let Some((encoding, _)) = Encoding::for_bom(body) {
+		return encoding;
+	}
+	if let Some(encoding) = content_type.and_then(content_type_charset) {
+		return encoding;
+	}
+	match declared_span(body).and_then(|span| Encoding::for_label(&body[span])) {
+		Some(encoding) if(std::str::from_utf8(body).is_err()) => encoding,
+		_ => UTF_8
+	}
+}
+
+/// Transcodes `body` to UTF-8 (see [`detect()`]), with its XML declaration updated to match so that the XML parser
+/// doesn't decode it a second time.  Malformed sequences become U+FFFD.
+pub(crate) fn to_utf8(body: Bytes, content_type: Option<&str>, forced: Option<&'static Encoding>) -> Bytes {
+	let encoding = detect(&body, content_type, forced);
+	let declares_other = |text: &str| declared_span(text.as_bytes()).map(|span| Encoding::for_label(text[span].as_bytes()) != Some(UTF_8)).unwrap_or(false);
+	let mut text = match encoding.decode_with_bom_removal(&body) {
+		(Cow::Borrowed(text), _) if(text.len() == body.len() && !declares_other(text)) => return body,
+		(text, _) => text.into_owned()
+	};
+	if let Some(span) = declared_span(text.as_bytes()) {
+		text.replace_range(span, "UTF-8");
+	}
+	text.into()
+}
+
+/// Undoes text having been encoded as UTF-8, decoded as windows-1252 or windows-1251, and encoded as UTF-8 again, as
+/// happens to titles that pass through an aggregator which guessed the wrong charset.  Text is only changed if
+/// reversing that yields valid UTF-8 with non-ASCII characters, which legitimate text in those charsets almost
+/// never does.
+pub(crate) fn repair_double_encoding(text: &str) -> Option<String> {
+	if(text.is_ascii()) {
+		return None;
+	}
+	IntoIterator::into_iter([WINDOWS_1252, WINDOWS_1251]).find_map(|encoding| {
+		let (bytes, _, unmappable) = encoding.encode(text);
+		if(unmappable || bytes.is_ascii()) {
+			return None;
+		}
+		std::str::from_utf8(&bytes).ok().map(str::to_string)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use encoding_rs::GBK;
+
+	use super::*;
+
+	#[test]
+	fn transcodes() {
+		let (body, _, _) = WINDOWS_1251.encode(r#"<?xml version="1.0" encoding="windows-1251"?><rss><title>Привет</title></rss>"#);
+		let utf8 = to_utf8(Bytes::from(body.into_owned()), None, None);
+		assert_eq!(utf8, r#"<?xml version="1.0" encoding="UTF-8"?><rss><title>Привет</title></rss>"#);
+
+		let (body, _, _) = GBK.encode("<rss><title>你好</title></rss>");
+		let body = Bytes::from(body.into_owned());
+		assert_eq!(to_utf8(body.clone(), Some("application/rss+xml; charset=\"GBK\""), None), "<rss><title>你好</title></rss>");
+		assert_eq!(to_utf8(body, Some("text/xml"), Some(GBK)), "<rss><title>你好</title></rss>");
+
+		// A declaration left over from before the feed was re-encoded as UTF-8.
+		let body = Bytes::from(r#"<?xml version='1.0' encoding='windows-1251'?><rss><title>Привет</title></rss>"#);
+		assert_eq!(to_utf8(body, None, None), r#"<?xml version='1.0' encoding='UTF-8'?><rss><title>Привет</title></rss>"#);
+		let body = Bytes::from(r#"<?xml version="1.0" encoding="utf-8"?><rss />"#);
+		assert_eq!(to_utf8(body.clone(), Some("text/xml; charset=utf-8"), None).as_ptr(), body.as_ptr());
+	}
+
+	#[test]
+	fn repairs_double_encoding() {
+		assert_eq!(repair_double_encoding("ÐŸÑ€Ð¸Ð²ÐµÑ‚.2021.1080p").as_deref(), Some("Привет.2021.1080p"));
+		assert_eq!(repair_double_encoding("РџСЂРёРІРµС‚.2021.1080p").as_deref(), Some("Привет.2021.1080p"));
+		assert_eq!(repair_double_encoding("CafÃ©.2021").as_deref(), Some("Café.2021"));
+		for text in ["Привет.2021.1080p", "Café.2021", "你好", "Plain.ASCII"] {
+			assert_eq!(repair_double_encoding(text), None);
+		}
+	}
+}
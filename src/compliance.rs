@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// Seeding a tracker expects of a grab before it stops counting as a hit-and-run, from the `minimumratio` and
+/// `minimumseedtime` attrs.
+pub struct SeedRequirements {
+	pub minimum_ratio: Option<f32>,
+	pub minimum_seedtime: Option<Duration>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// How a grab stands against its tracker's [`SeedRequirements`].
+pub struct Compliance {
+	/// Uploaded bytes relative to downloaded ones, as the tracker credits them.
+	pub ratio: f64,
+	/// Whether the ratio requirement is met; `None` if there isn't one.
+	pub ratio_met: Option<bool>,
+	/// Whether the seed time requirement is met; `None` if there isn't one.
+	pub seedtime_met: Option<bool>,
+	/// Bytes still to upload to meet the ratio requirement, before the tracker's upload volume factor; `u64::MAX` if
+	/// no amount would, because uploads aren't credited at all.
+	pub remaining_upload: u64,
+	/// Time still to seed to meet the seed time requirement.
+	pub remaining_seedtime: Duration
+}
+
+impl SeedRequirements {
+	pub fn is_empty(&self) -> bool {
+		self.minimum_ratio.is_none() && self.minimum_seedtime.is_none()
+	}
+
+	/// Checks a grab of a `size`-byte torrent that has so far uploaded `uploaded` bytes and seeded for `seeded`, with
+	/// uploads and downloads credited in full.  `uploaded` is the raw amount, as the torrent client counts it.
+	pub fn check(&self, size: u64, uploaded: u64, seeded: Duration) -> Compliance {
+		self.check_with_factors(size, uploaded, seeded, 1.0, 1.0)
+	}
+
+	/// Like [`check()`](Self::check), but with the tracker crediting `download_volume_factor` of the download and
+	/// `upload_volume_factor` times the upload, as the attrs of the same names say; so freeleech grabs have no upload
+	/// to make up, and double upload ones half as much.  Factors that are negative or not finite count as `1`.
+	pub fn check_with_factors(&self, size: u64, uploaded: u64, seeded: Duration, download_volume_factor: f32, upload_volume_factor: f32) -> Compliance {
+		let factor = |factor: f32| if(factor.is_finite() && factor >= 0.0) { f64::from(factor) } else { 1.0 };
+		let (download_factor, upload_factor) = (factor(download_volume_factor), factor(upload_volume_factor));
+		let downloaded = size as f64 * download_factor;
+		let ratio = if(downloaded == 0.0) { f64::INFINITY } else { uploaded as f64 * upload_factor / downloaded };
+		let required_upload = self.minimum_ratio.map(|ratio| match downloaded * f64::from(ratio) {
+			required if(required <= 0.0) => 0,
+			_ if(upload_factor == 0.0) => u64::MAX,
+			required => (required / upload_factor).ceil() as u64
+		});
+		Compliance{
+			ratio,
+			ratio_met: required_upload.map(|required| required != u64::MAX && uploaded >= required),
+			seedtime_met: self.minimum_seedtime.map(|required| seeded >= required),
+			remaining_upload: match required_upload {
+				Some(u64::MAX) => u64::MAX,
+				Some(required) => required.saturating_sub(uploaded),
+				None => 0
+			},
+			remaining_seedtime: self.minimum_seedtime.map(|required| required.saturating_sub(seeded)).unwrap_or_default()
+		}
+	}
+}
+
+impl Compliance {
+	/// Whether the grab can be stopped without counting as a hit-and-run.  As with most private trackers' rules,
+	/// meeting either the ratio or the seed time requirement is enough; a grab with no requirements is always satisfied.
+	pub fn is_satisfied(&self) -> bool {
+		match (self.ratio_met, self.seedtime_met) {
+			(None, None) => true,
+			(ratio, seedtime) => ratio == Some(true) || seedtime == Some(true)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn either_requirement_satisfies() {
+		let requirements = SeedRequirements{minimum_ratio: Some(1.0), minimum_seedtime: Some(Duration::from_secs(3600))};
+		let compliance = requirements.check(1000, 400, Duration::from_secs(600));
+		assert!(!compliance.is_satisfied());
+		assert_eq!(compliance.remaining_upload, 600);
+		assert_eq!(compliance.remaining_seedtime, Duration::from_secs(3000));
+		assert!(requirements.check(1000, 1000, Duration::ZERO).is_satisfied());
+		assert!(requirements.check(1000, 0, Duration::from_secs(3600)).is_satisfied());
+		assert!(SeedRequirements::default().check(1000, 0, Duration::ZERO).is_satisfied());
+	}
+
+	#[test]
+	fn volume_factors() {
+		let requirements = SeedRequirements{minimum_ratio: Some(1.0), minimum_seedtime: None};
+		let freeleech = requirements.check_with_factors(1000, 0, Duration::ZERO, 0.0, 1.0);
+		assert_eq!((freeleech.ratio_met, freeleech.remaining_upload, freeleech.ratio), (Some(true), 0, f64::INFINITY));
+		let double_upload = requirements.check_with_factors(1000, 400, Duration::ZERO, 1.0, 2.0);
+		assert_eq!((double_upload.ratio_met, double_upload.remaining_upload, double_upload.ratio), (Some(false), 100, 0.8));
+		let halfleech = requirements.check_with_factors(1000, 500, Duration::ZERO, 0.5, 1.0);
+		assert_eq!((halfleech.ratio_met, halfleech.remaining_upload), (Some(true), 0));
+		let uncredited = requirements.check_with_factors(1000, 5000, Duration::ZERO, 1.0, 0.0);
+		assert_eq!((uncredited.ratio_met, uncredited.remaining_upload), (Some(false), u64::MAX));
+		assert_eq!(requirements.check_with_factors(1000, 400, Duration::ZERO, f32::NAN, f32::INFINITY), requirements.check(1000, 400, Duration::ZERO));
+	}
+}
@@ -0,0 +1,117 @@
+use chrono::DateTime;
+use chrono::FixedOffset;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::TimeZone;
+use chrono::Utc;
+
+/// Formats without a UTC offset, seen from Jackett and a few Newznab implementations; these are assumed to be UTC.
+const NAIVE_FORMATS: &[&str] = &[
+	"%Y-%m-%d %H:%M:%S",
+	"%Y-%m-%dT%H:%M:%S%.f",
+	"%Y-%m-%d %H:%M:%S%.f"
+];
+
+/// Times that follow a slash-separated date, which is also assumed to be UTC.
+const SLASH_TIME_FORMATS: &[&str] = &[
+	"%H:%M:%S",
+	"%I:%M:%S %p"
+];
+
+/// Formats with an offset that neither RFC 2822 nor RFC 3339 parsing accepts.
+const OFFSET_FORMATS: &[&str] = &[
+	"%a, %d %b %Y %H:%M:%S %z",
+	"%A, %d %b %Y %H:%M:%S %z",
+	"%a, %d %B %Y %H:%M:%S %z",
+	"%d %b %Y %H:%M:%S %z",
+	"%Y-%m-%d %H:%M:%S %z",
+	"%Y-%m-%d %H:%M:%S%.f%:z"
+];
+
+/// Parses a `pubDate` or `usenetdate`.  RFC 2822 is what the specs call for, but indexers emit all sorts of things, so
+/// this also accepts ISO 8601/RFC 3339, Unix timestamps, long weekday/month names, and a handful of offset-less formats
+/// (which are taken to be UTC).  Slash-separated dates are only accepted if they read the same day-first and
+/// month-first, or only one way round makes a valid date.
+pub(crate) fn parse_date(s: &str) -> Result<DateTime<FixedOffset>, chrono::ParseError> {
+	let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+	let rfc2822 = match DateTime::parse_from_rfc2822(&s) {
+		Ok(date) => return Ok(date),
+		Err(e) => e
+	};
+	if let Ok(date) = DateTime::parse_from_rfc3339(&s) {
+		return Ok(date);
+	}
+	for format in OFFSET_FORMATS {
+		if let Ok(date) = DateTime::parse_from_str(&s, format) {
+			return Ok(date);
+		}
+	}
+	let utc = FixedOffset::east_opt(0).unwrap();
+	for format in NAIVE_FORMATS {
+		if let Ok(date) = NaiveDateTime::parse_from_str(&s, format) {
+			return Ok(utc.from_utc_datetime(&date));
+		}
+	}
+	if let Some(date) = slash_date(&s) {
+		return Ok(utc.from_utc_datetime(&date));
+	}
+	if let Ok(date) = NaiveDate::parse_from_str(&s, "%Y-%m-%d") {
+		return Ok(utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()));
+	}
+	if let Ok(timestamp) = s.parse::<i64>() {
+		if let Some(date) = Utc.timestamp_opt(timestamp, 0).single() {
+			return Ok(date.fixed_offset());
+		}
+	}
+	Err(rfc2822)
+}
+
+/// Parses `dd/mm/yyyy` or `mm/dd/yyyy`, whichever is unambiguous.
+fn slash_date(s: &str) -> Option<NaiveDateTime> {
+	for time in SLASH_TIME_FORMATS {
+		let day_first = NaiveDateTime::parse_from_str(s, &format!("%d/%m/%Y {}", time)).ok();
+		let month_first = NaiveDateTime::parse_from_str(s, &format!("%m/%d/%Y {}", time)).ok();
+		match (day_first, month_first) {
+			(Some(a), Some(b)) => return Some(a).filter(|a| *a == b),
+			(Some(date), None) | (None, Some(date)) => return Some(date),
+			(None, None) => {}
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn variants() {
+		let expected = DateTime::parse_from_rfc3339("2021-04-03T09:29:49Z").unwrap();
+		for s in [
+			"Sat, 03 Apr 2021 05:29:49 -0400",
+			"Sat,  3 Apr 2021 05:29:49 -0400",
+			"03 Apr 2021 09:29:49 GMT",
+			"Saturday, 03 Apr 2021 05:29:49 -0400",
+			"Sat, 03 April 2021 05:29:49 -0400",
+			"2021-04-03T05:29:49-04:00",
+			"2021-04-03T05:29:49.0000000-04:00",
+			"2021-04-03 09:29:49",
+			"2021-04-03T09:29:49",
+			"1617442189"
+		] {
+			assert_eq!(parse_date(s).unwrap(), expected, "{}", s);
+		}
+		assert!(parse_date("yesterday").is_err());
+	}
+
+	#[test]
+	fn slash_dates() {
+		let expected = DateTime::parse_from_rfc3339("2021-04-13T09:29:49Z").unwrap();
+		assert_eq!(parse_date("13/04/2021 09:29:49").unwrap(), expected);
+		assert_eq!(parse_date("04/13/2021 09:29:49").unwrap(), expected);
+		assert_eq!(parse_date("04/13/2021 09:29:49 AM").unwrap(), expected);
+		assert_eq!(parse_date("04/04/2021 09:29:49").unwrap(), DateTime::parse_from_rfc3339("2021-04-04T09:29:49Z").unwrap());
+		assert!(parse_date("03/04/2021 09:29:49").is_err());
+		assert!(parse_date("03/04/2021 09:29:49 PM").is_err());
+	}
+}
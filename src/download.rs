@@ -0,0 +1,56 @@
+use bytes::Bytes;
+use tracing::instrument;
+
+use crate::nzb;
+use crate::telemetry;
+use crate::Client;
+use crate::Error;
+use crate::Nzb;
+use crate::Protocol;
+use crate::SearchResult;
+
+#[derive(Clone, Debug)]
+/// What a result's link resolved to.
+pub enum Download {
+	/// A magnet URI, either linked directly or redirected to by the indexer.
+	Magnet(String),
+	/// The contents of a `.torrent` file.
+	Torrent(Bytes),
+	/// A parsed NZB, already checked against the size the indexer advertised.
+	Nzb(Nzb)
+}
+
+impl Client {
+	/// Fetches whatever `result` links to.  NZBs are parsed and validated against the result's size (see
+	/// [`Nzb::validate_size()`]); results whose size is unknown skip that check.
+	#[instrument(err, level = "info", skip(self, result), fields(name = %result.name))]
+	pub async fn download(&self, result: &SearchResult) -> Result<Download, Error> {
+		if(result.link.starts_with("magnet:")) {
+			return Ok(Download::Magnet(result.link.clone()));
+		}
+		let link: url::Url = result.link.parse()?;
+		let response = self.fetch(|_| link.clone()).await?;
+		if(response.is_redirection()) {
+			if let Some(location) = response.header("Location") {
+				if(location.starts_with("magnet:")) {
+					return Ok(Download::Magnet(location.to_string()));
+				}
+			}
+		}
+		if(!response.is_success()) {
+			return Err(Error::Status(response.status));
+		}
+		let bytes = response.body;
+		telemetry::bytes_received(&self.name, bytes.len());
+		match result.protocol {
+			Protocol::Torrent => Ok(Download::Torrent(bytes)),
+			Protocol::Usenet => {
+				let nzb = Nzb::parse(&bytes)?;
+				if(result.size > 0) {
+					nzb.validate_size(result.size, nzb::SIZE_TOLERANCE)?;
+				}
+				Ok(Download::Nzb(nzb))
+			}
+		}
+	}
+}
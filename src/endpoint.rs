@@ -0,0 +1,86 @@
+use url::Url;
+
+use crate::Error;
+
+/// Parses and checks the root URL of a service, e.g. a Jackett or Prowlarr instance.
+pub(crate) fn parse_base(base_url: &str) -> Result<Url, Error> {
+	let url = Url::parse(base_url)?;
+	if(!matches!(url.scheme(), "http" | "https")) {
+		return Err(Error::UnsupportedScheme(url.scheme().to_string()));
+	}
+	if(url.cannot_be_a_base()) {
+		return Err(Error::InvalidValue("base_url"));
+	}
+	Ok(url)
+}
+
+/// Parses a Torznab or Newznab endpoint.  Its path may or may not end in `/api`; if it doesn't, `/api` is appended.
+/// Any query string is kept, and merged with each request's parameters by [`with_query()`].
+pub(crate) fn parse_api(base_url: &str) -> Result<Url, Error> {
+	let mut url = parse_base(base_url)?;
+	if(url.path_segments().and_then(|mut segments| segments.rfind(|segment| !segment.is_empty())) != Some("api")) {
+		url = join(&url, &["api"]);
+	}
+	Ok(url)
+}
+
+/// `base_url` with any password and query string, which may hold credentials, removed; for logging.
+#[cfg(feature = "reqwest")]
+pub(crate) fn redacted(base_url: &str) -> String {
+	match Url::parse(base_url) {
+		Ok(mut url) => {
+			let _ = url.set_password(None);
+			url.set_query(None);
+			url.to_string()
+		},
+		Err(_) => "<invalid>".to_string()
+	}
+}
+
+/// Appends percent-encoded path segments to `base`, ignoring any trailing slash it has.
+pub(crate) fn join(base: &Url, segments: &[&str]) -> Url {
+	let mut url = base.clone();
+	url.path_segments_mut().expect("base URL was checked by parse_base()").pop_if_empty().extend(segments);
+	url
+}
+
+/// Merges `params` into `base`'s query string; parameters already present in `base` are replaced.
+pub(crate) fn with_query<'a>(base: &Url, params: impl IntoIterator<Item = (&'a str, &'a str)>) -> Url {
+	let params: Vec<_> = params.into_iter().collect();
+	let mut url = base.clone();
+	let kept: Vec<(String, String)> = base.query_pairs()
+		.filter(|(k, _)| !params.iter().any(|(param, _)| param == k))
+		.map(|(k, v)| (k.into_owned(), v.into_owned()))
+		.collect();
+	url.query_pairs_mut().clear().extend_pairs(kept).extend_pairs(params);
+	if(url.query() == Some("")) {
+		url.set_query(None);
+	}
+	url
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn api_paths() {
+		assert_eq!(parse_api("http://localhost:9117/api/v2.0/indexers/all/results/torznab").unwrap().as_str(), "http://localhost:9117/api/v2.0/indexers/all/results/torznab/api");
+		assert_eq!(parse_api("http://localhost:9117/api/v2.0/indexers/all/results/torznab/api").unwrap().as_str(), "http://localhost:9117/api/v2.0/indexers/all/results/torznab/api");
+		assert_eq!(parse_api("http://localhost:9696/1/").unwrap().as_str(), "http://localhost:9696/1/api");
+		assert_eq!(parse_api("https://api.nzbgeek.info").unwrap().as_str(), "https://api.nzbgeek.info/api");
+		assert!(matches!(parse_api("ftp://localhost/api"), Err(Error::UnsupportedScheme(_))));
+		assert!(parse_api("localhost:9117/api").is_err());
+		assert!(matches!(parse_api("not a url"), Err(Error::InvalidUrl(_))));
+	}
+
+	#[test]
+	fn merged_query() {
+		let base = parse_api("http://localhost/api?apikey=old&t=search&o=xml").unwrap();
+		let url = with_query(&base, vec![("apikey", "new"), ("q", "50% off & more")]);
+		assert_eq!(url.as_str(), "http://localhost/api?t=search&o=xml&apikey=new&q=50%25+off+%26+more");
+		let base = parse_api("http://localhost/api").unwrap();
+		assert_eq!(with_query(&base, vec![]).as_str(), "http://localhost/api");
+		assert_eq!(join(&base, &["tag:anime fans", "a/b"]).as_str(), "http://localhost/api/tag:anime%20fans/a%2Fb");
+	}
+}
@@ -2,26 +2,99 @@
 pub enum Error {
 	#[error("missing expected field `{0}`")]
 	MissingField(String),
+	#[error("invalid URL")]
+	InvalidUrl(#[from] url::ParseError),
+	#[error("unsupported URL scheme `{0}`")]
+	UnsupportedScheme(String),
 	#[error("HTTP error")]
-	Reqwest(#[from] reqwest::Error),
-	#[error("failed to execute task")]
-	JoinError(#[from] tokio::task::JoinError),
-	#[error("RSS error")]
-	RSS(#[from] rss::Error),
+	Transport(#[from] crate::transport::TransportError),
+	#[error("HTTP status {0}")]
+	Status(u16),
+	#[error("failed to refresh credentials")]
+	Credentials(#[source] crate::auth::RefreshError),
+	#[error("I/O error")]
+	Io(#[from] std::io::Error),
+	#[error("failed to parse JSON")]
+	Json(#[from] serde_json::Error),
+	#[error("indexer returned error {}: {description}", .code.map(|code| code.to_string()).unwrap_or_default())]
+	Indexer{
+		code: Option<u32>,
+		description: String
+	},
+	#[error("invalid Jackett filter: {0}")]
+	InvalidFilter(String),
+	#[error("indexer doesn't support {0:?}")]
+	UnsupportedMode(crate::SearchMode),
+	#[error("indexer doesn't support searching by `{0}`, and there's no title to search for instead")]
+	UnsupportedParam(&'static str),
+	#[error("indexer is backing off after repeated failures; retry in {retry_in:?}")]
+	CircuitOpen{
+		retry_in: std::time::Duration
+	},
+	#[error("response body exceeds the limit of {limit} bytes")]
+	BodyTooLarge{
+		limit: usize
+	},
+	#[error("feed has more than the limit of {limit} items")]
+	TooManyItems{
+		limit: usize
+	},
+	#[error("item has more than the limit of {limit} attrs")]
+	TooManyAttrs{
+		limit: usize
+	},
+	#[error("string exceeds the limit of {limit} bytes")]
+	StringTooLong{
+		limit: usize
+	},
+	#[error("document isn't an RSS feed")]
+	NotAFeed,
+	#[error("document ended before the feed did")]
+	UnexpectedEof,
+	#[error("XML error")]
+	Xml(#[from] quick_xml::Error),
+	#[error("item {}: bad field `{field}`", .guid.as_deref().unwrap_or("<no guid>"))]
+	Item{
+		guid: Option<String>,
+		field: &'static str,
+		value: Option<String>,
+		#[source]
+		source: Box<Error>
+	},
 	#[error("missing title")]
 	MissingTitle,
 	#[error("missing size")]
 	MissingSize,
 	#[error("missing link")]
 	MissingLink,
+	#[error("unrecognized value for `{0}`")]
+	InvalidValue(&'static str),
+	#[error("NZB totals {actual} bytes, but the indexer advertised {advertised}")]
+	NzbSizeMismatch{
+		advertised: u64,
+		actual: u64
+	},
 	#[error("empty extension `{0}`")]
 	EmptyExtension(String),
 	#[error("failed to parse integer")]
 	ParseInt(#[from] std::num::ParseIntError),
 	#[error("failed to parse float")]
 	ParseFloat(#[from] std::num::ParseFloatError),
+	#[error("failed to parse date")]
+	ParseDate(#[from] chrono::ParseError),
 	#[cfg(feature = "require-parse-names")]
 	#[error("failed to parse torrent name")]
 	ParseTorrentName(#[from] torrent_name_parser::error::ErrorMatch)
 }
 
+impl Error {
+	/// Whether this says the indexer is unavailable, i.e. couldn't be reached, failed with a `5xx` or is rate limiting,
+	/// as opposed to a problem with the request or the response's content.  Only these count against its health.
+	pub(crate) fn is_unavailable(&self) -> bool {
+		match self {
+			Self::Transport(_) => true,
+			Self::Status(status) => *status == 429 || (500..600).contains(status),
+			_ => false
+		}
+	}
+}
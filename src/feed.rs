@@ -0,0 +1,345 @@
+use crate::IndexerSource;
+
+/// The `torznab:attr`s and `newznab:attr`s that items are parsed from.  Others are dropped as they're read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Attr {
+	Category,
+	DownloadVolumeFactor,
+	Grabs,
+	Group,
+	Indexer,
+	Leechers,
+	MagnetUrl,
+	MinimumRatio,
+	MinimumSeedtime,
+	Password,
+	Peers,
+	Poster,
+	Seeders,
+	Size,
+	UploadVolumeFactor,
+	UsenetDate
+}
+
+const ATTRS: usize = 16;
+
+impl Attr {
+	pub fn from_name(name: &str) -> Option<Self> {
+		Some(match name {
+			"category" => Self::Category,
+			"downloadvolumefactor" => Self::DownloadVolumeFactor,
+			"grabs" => Self::Grabs,
+			"group" => Self::Group,
+			"indexer" => Self::Indexer,
+			"leechers" => Self::Leechers,
+			"magneturl" => Self::MagnetUrl,
+			"minimumratio" => Self::MinimumRatio,
+			"minimumseedtime" => Self::MinimumSeedtime,
+			"password" => Self::Password,
+			"peers" => Self::Peers,
+			"poster" => Self::Poster,
+			"seeders" => Self::Seeders,
+			"size" => Self::Size,
+			"uploadvolumefactor" => Self::UploadVolumeFactor,
+			"usenetdate" => Self::UsenetDate,
+			_ => return None
+		})
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Category => "category",
+			Self::DownloadVolumeFactor => "downloadvolumefactor",
+			Self::Grabs => "grabs",
+			Self::Group => "group",
+			Self::Indexer => "indexer",
+			Self::Leechers => "leechers",
+			Self::MagnetUrl => "magneturl",
+			Self::MinimumRatio => "minimumratio",
+			Self::MinimumSeedtime => "minimumseedtime",
+			Self::Password => "password",
+			Self::Peers => "peers",
+			Self::Poster => "poster",
+			Self::Seeders => "seeders",
+			Self::Size => "size",
+			Self::UploadVolumeFactor => "uploadvolumefactor",
+			Self::UsenetDate => "usenetdate"
+		}
+	}
+}
+
+/// Attr namespaces, in order of precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Namespace {
+	Torznab,
+	Newznab
+}
+
+impl Namespace {
+	pub fn from_prefix(prefix: &[u8]) -> Option<Self> {
+		match prefix {
+			b"torznab" => Some(Self::Torznab),
+			b"newznab" => Some(Self::Newznab),
+			_ => None
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct NamespaceAttrs {
+	present: bool,
+	/// The first value of each [`Attr`], indexed by discriminant.
+	first: [Option<String>; ATTRS],
+	/// Values after the first, for the attrs that may repeat.
+	rest: Vec<(Attr, String)>
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// An item's attrs, indexed by name as they're read so that looking one up doesn't scan the rest.
+pub(crate) struct Attrs([NamespaceAttrs; 2]);
+
+impl Attrs {
+	/// Records that the item has an element in `namespace`, whether or not it's an attr.
+	pub fn touch(&mut self, namespace: Namespace) {
+		self.0[namespace as usize].present = true;
+	}
+
+	pub fn push(&mut self, namespace: Namespace, attr: Attr, value: String) {
+		let attrs = &mut self.0[namespace as usize];
+		attrs.present = true;
+		match &mut attrs.first[attr as usize] {
+			slot @ None => *slot = Some(value),
+			Some(_) => attrs.rest.push((attr, value))
+		}
+	}
+
+	pub fn has(&self, namespace: Namespace) -> bool {
+		self.0[namespace as usize].present
+	}
+
+	/// The first value of `attr`, preferring `torznab:attr` to `newznab:attr`.
+	pub fn get(&self, attr: Attr) -> Option<&str> {
+		self.0.iter().find_map(|attrs| attrs.first[attr as usize].as_deref())
+	}
+
+	/// Every value of `attr`, `torznab:attr`s first.
+	pub fn all(&self, attr: Attr) -> impl Iterator<Item = &str> {
+		self.0.iter().flat_map(move |attrs| attrs.first[attr as usize].as_deref().into_iter()
+			.chain(attrs.rest.iter().filter(move |(a, _)| *a == attr).map(|(_, value)| value.as_str())))
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Enclosure {
+	pub url: String,
+	pub length: String,
+	pub mime_type: String
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The parts of an `<item>` that [`SearchResult`](crate::SearchResult)s are built from.
+pub(crate) struct FeedItem {
+	pub title: Option<String>,
+	pub guid: Option<String>,
+	pub link: Option<String>,
+	pub pub_date: Option<String>,
+	pub categories: Vec<String>,
+	pub enclosure: Option<Enclosure>,
+	pub attrs: Attrs,
+	/// Un-namespaced `<size>` element, which indexers emit outside the RSS and Torznab specs.
+	pub size: Option<String>,
+	/// Jackett's `<jackettindexer>` and Prowlarr's `<prowlarrindexer>` elements, in that order.
+	pub indexers: [Option<IndexerSource>; 2]
+}
+
+/// The `rss`-based reader that [`FeedReader`](crate::FeedReader) replaced, kept to check the two agree.
+#[cfg(test)]
+pub(crate) mod via_rss {
+	use std::io::BufRead;
+
+	use quick_xml::events::Event;
+	use quick_xml::Reader;
+	use rss::Channel;
+	use rss::Item;
+
+	use super::*;
+	use crate::caps::indexer_error;
+	use crate::xml::attr;
+	use crate::xml::element_text;
+	use crate::xml::skip;
+	use crate::ChannelInfo;
+	use crate::Error;
+
+	impl From<Item> for FeedItem {
+		fn from(item: Item) -> Self {
+			let mut attrs = Attrs::default();
+			for namespace in [Namespace::Torznab, Namespace::Newznab] {
+				let prefix = match namespace {
+					Namespace::Torznab => "torznab",
+					Namespace::Newznab => "newznab"
+				};
+				let extensions = match item.extensions().get(prefix) {
+					Some(v) => v,
+					None => continue
+				};
+				attrs.touch(namespace);
+				for extension in extensions.get("attr").into_iter().flatten() {
+					if let (Some(name), Some(value)) = (extension.attrs().get("name"), extension.attrs().get("value")) {
+						if let Some(attr) = Attr::from_name(name) {
+							attrs.push(namespace, attr, value.clone());
+						}
+					}
+				}
+			}
+			Self{
+				enclosure: item.enclosure().map(|enclosure| Enclosure{
+					url: enclosure.url().to_string(),
+					length: enclosure.length().to_string(),
+					mime_type: enclosure.mime_type().to_string()
+				}),
+				categories: item.categories().iter().map(|category| category.name().to_string()).collect(),
+				guid: item.guid.map(|guid| guid.value),
+				title: item.title,
+				link: item.link,
+				pub_date: item.pub_date,
+				attrs,
+				size: None,
+				indexers: Default::default()
+			}
+		}
+	}
+
+	pub(crate) fn read_items(bytes: &[u8]) -> Result<Vec<FeedItem>, Error> {
+		read_channel(bytes).map(|(_, items)| items)
+	}
+
+	/// Reads a feed with `rss`, which holds the whole document in memory, as this crate did before
+	/// [`FeedReader`](crate::FeedReader).  `rss` drops un-namespaced elements that aren't part of RSS, so
+	/// those are picked up in a second pass over the document.
+	pub(crate) fn read_channel(bytes: &[u8]) -> Result<(ChannelInfo, Vec<FeedItem>), Error> {
+		let mut channel = match Channel::read_from(bytes) {
+			Ok(channel) => channel,
+			Err(_) => return Err(read_error(bytes).unwrap_or(Error::NotAFeed))
+		};
+		let info = channel_info(&channel);
+		let mut extras = read_extras(bytes)?.into_iter();
+		let items = channel.items.drain(..).map(|item| {
+			let (size, indexers) = extras.next().unwrap_or_default();
+			FeedItem{size, indexers, ..item.into()}
+		}).collect();
+		Ok((info, items))
+	}
+
+	fn channel_info(channel: &Channel) -> ChannelInfo {
+		let response = ["newznab", "torznab"].iter()
+			.filter_map(|namespace| channel.extensions().get(*namespace)?.get("response")?.first())
+			.next();
+		let response_attr = |key: &str| response.and_then(|response| response.attrs().get(key)).and_then(|value| value.trim().parse().ok());
+		ChannelInfo{
+			title: channel.title().to_string(),
+			link: channel.link().to_string(),
+			description: channel.description().to_string(),
+			language: channel.language().map(str::to_string),
+			offset: response_attr("offset"),
+			total: response_attr("total")
+		}
+	}
+
+	/// Reads the [`Error::Indexer`] out of a document that isn't a feed, but an indexer's `<error>` response.
+	fn read_error(bytes: &[u8]) -> Option<Error> {
+		let mut reader = Reader::from_reader(bytes);
+		let mut buf = Vec::new();
+		loop {
+			match reader.read_event_into(&mut buf).ok()? {
+				Event::Start(element) | Event::Empty(element) => return match element.local_name().as_ref() {
+					b"error" => indexer_error(&reader, &element).ok(),
+					_ => None
+				},
+				Event::Eof => return None,
+				_ => {}
+			}
+			buf.clear();
+		}
+	}
+
+	type Extras = (Option<String>, [Option<IndexerSource>; 2]);
+
+	fn read_extras(bytes: &[u8]) -> Result<Vec<Extras>, Error> {
+		let mut reader = Reader::from_reader(bytes);
+		reader.config_mut().expand_empty_elements = true;
+		let mut buf = Vec::new();
+		let mut items = Vec::new();
+		loop {
+			match reader.read_event_into(&mut buf)? {
+				Event::Start(element) if element.name().as_ref() == b"item" => items.push(read_item_extras(&mut reader)?),
+				Event::Eof => break,
+				_ => {}
+			}
+			buf.clear();
+		}
+		Ok(items)
+	}
+
+	fn read_item_extras<R: BufRead>(reader: &mut Reader<R>) -> Result<Extras, Error> {
+		let mut extras = Extras::default();
+		let mut buf = Vec::new();
+		loop {
+			match reader.read_event_into(&mut buf)? {
+				Event::Start(element) => match element.name().as_ref() {
+					b"size" => extras.0 = element_text(reader)?,
+					b"jackettindexer" => extras.1[0] = Some(IndexerSource{id: attr(reader, &element, b"id")?, name: element_text(reader)?}),
+					b"prowlarrindexer" => extras.1[1] = Some(IndexerSource{id: attr(reader, &element, b"id")?, name: element_text(reader)?}),
+					_ => skip(reader, &element)?
+				},
+				Event::End(_) | Event::Eof => break,
+				_ => {}
+			}
+			buf.clear();
+		}
+		Ok(extras)
+	}
+
+	#[test]
+	fn agrees_with_streaming() {
+		let feed = br#"<?xml version="1.0" encoding="UTF-8"?>
+			<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed" xmlns:newznab="http://www.newznab.com/DTD/2010/feeds/attributes/">
+			  <channel>
+				<title>Jackett</title>
+				<link>http://127.0.0.1:9117/</link>
+				<description>Jackett aggregate indexer</description>
+				<language>en-US</language>
+				<torznab:response offset="0" total="2" />
+				<item>
+				  <title>Some.Release.2021.1080p.WEB-DL.DDP5.1.H.264-GRP</title>
+				  <guid>http://127.0.0.1:9117/details/1</guid>
+				  <jackettindexer id="rarbg">RARBG</jackettindexer>
+				  <pubDate>Sat, 03 Apr 2021 05:29:49 -0400</pubDate>
+				  <size>1000</size>
+				  <link>http://127.0.0.1:9117/dl/rarbg/1</link>
+				  <category>2000</category>
+				  <enclosure url="http://127.0.0.1:9117/dl/rarbg/1" length="1000" type="application/x-bittorrent" />
+				  <torznab:attr name="category" value="2000" />
+				  <torznab:attr name="seeders" value="7" />
+				  <torznab:attr name="peers" value="9" />
+				  <torznab:attr name="downloadvolumefactor" value="0" />
+				</item>
+				<item>
+				  <title>Some.Show.S01E01</title>
+				  <guid>2</guid>
+				  <prowlarrindexer id="4">NZBgeek</prowlarrindexer>
+				  <link>https://nzbgeek.info/get/2</link>
+				  <enclosure url="https://nzbgeek.info/get/2" length="2000" type="application/x-nzb" />
+				  <newznab:attr name="category" value="5030" />
+				  <newznab:attr name="grabs" value="12" />
+				</item>
+			  </channel>
+			</rss>
+		"#;
+		let options = crate::ParseOptions::default();
+		let streamed = options.parse_feed(feed).unwrap();
+		let (channel, items) = read_channel(feed).unwrap();
+		assert_eq!(streamed.channel, channel);
+		let via_rss = items.into_iter().map(|item| crate::torrent::from_item(item, options).unwrap()).collect::<Vec<_>>();
+		assert_eq!(streamed.results.into_iter().map(Result::unwrap).collect::<Vec<_>>(), via_rss);
+	}
+}
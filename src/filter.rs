@@ -0,0 +1,582 @@
+//! Declarative filtering of search results.
+//!
+//! A [`Filter`] is a list of [`Rule`]s, all of which a result must pass.  Rules deserialize from an externally
+//! tagged form, e.g. in JSON:
+//!
+//! ```json
+//! [
+//!     {"min_seeders": 5},
+//!     {"size": {"categories": [2000], "max": 20000000000}},
+//!     {"excluded_words": ["cam", "telesync"]},
+//!     {"named": {"name": "no x265", "rule": {"not": {"matches": {"field": "name", "regex": "(?i)x265|hevc"}}}}},
+//!     {"any": ["freeleech", {"compare": {"field": "seeders", "op": ">=", "value": 50}}]}
+//! ]
+//! ```
+//!
+//! Every rejection comes with a [`Rejection`] explaining which rule failed and why, fit to show to a user.
+
+use std::fmt;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+use regex::Regex;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::SearchResult;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+/// A condition on a [`SearchResult`].
+pub enum Rule {
+	/// Passes if every rule passes.
+	All(Vec<Rule>),
+	/// Passes if at least one rule passes; otherwise the rejection gives every rule's reason.
+	Any(Vec<Rule>),
+	/// Passes if the rule fails.
+	Not(Box<Rule>),
+	/// Gives a rule a name, which is reported in rejections it causes.
+	Named{
+		name: String,
+		rule: Box<Rule>
+	},
+	/// Passes if `field` is present and matches `regex`.  Use `(?i)` for a case-insensitive match.
+	Matches{
+		field: TextField,
+		#[serde(with = "regex_string")]
+		regex: Regex
+	},
+	/// Passes if `field` is known and compares to `value` as given.
+	Compare{
+		field: NumericField,
+		op: Comparison,
+		value: f64
+	},
+	/// Bounds the size, in bytes, of results in any of `categories` or their subcategories, or of all results if
+	/// `categories` is empty.  Results in other categories pass.
+	Size{
+		#[serde(default)]
+		categories: Vec<u32>,
+		#[serde(default)]
+		min: Option<u64>,
+		#[serde(default)]
+		max: Option<u64>
+	},
+	/// Results with an unknown number of seeders fail.
+	MinSeeders(u32),
+	/// Passes if the name contains every one of these words, case-insensitively.  A word may span several tokens of
+	/// the name, e.g. `web-dl` matches `WEB.DL`.
+	RequiredWords(Vec<String>),
+	/// Passes if the name contains none of these words; see [`RequiredWords`](Self::RequiredWords).
+	ExcludedWords(Vec<String>),
+	/// Passes if the release group, the part of the name after its last `-`, is one of these, case-insensitively.
+	ReleaseGroups(Vec<String>),
+	/// Passes if the release is known to have been published at most this many seconds ago.
+	MaxAge(#[serde(with = "seconds")] Duration),
+	/// Passes if downloading the release doesn't count against ratio; see [`SearchResult::is_freeleech()`].
+	Freeleech,
+	/// Passes if the resolution in the name, e.g. `1080p`, is one of these.  `4k` and `uhd` count as `2160p`.
+	Resolutions(Vec<String>)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+/// A textual field of a [`SearchResult`], for [`Rule::Matches`].
+pub enum TextField {
+	Name,
+	Guid,
+	Link,
+	/// The name of the indexer the result came from, or its ID if it has no name.
+	Indexer
+}
+
+impl TextField {
+	fn get(self, result: &SearchResult) -> Option<&str> {
+		match self {
+			Self::Name => Some(&result.name),
+			Self::Guid => result.guid.as_deref(),
+			Self::Link => Some(&result.link),
+			Self::Indexer => result.indexer.as_ref().and_then(|indexer| indexer.name.as_deref().or(indexer.id.as_deref()))
+		}
+	}
+}
+
+impl fmt::Display for TextField {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Name => "name",
+			Self::Guid => "GUID",
+			Self::Link => "link",
+			Self::Indexer => "indexer"
+		})
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+/// A numeric field of a [`SearchResult`], for [`Rule::Compare`].
+pub enum NumericField {
+	/// In bytes.
+	Size,
+	Seeders,
+	Leechers,
+	Peers,
+	Grabs,
+	/// In seconds.
+	Age,
+	DownloadVolumeFactor,
+	UploadVolumeFactor,
+	MinimumRatio
+}
+
+impl NumericField {
+	fn get(self, result: &SearchResult, now: DateTime<Utc>) -> Option<f64> {
+		match self {
+			Self::Size => Some(result.size as f64),
+			Self::Seeders => result.seeders.map(f64::from),
+			Self::Leechers => result.leechers.map(f64::from),
+			Self::Peers => result.peers.map(f64::from),
+			Self::Grabs => result.grabs.map(f64::from),
+			Self::Age => result.age_at(now).map(|age| age.as_secs() as f64),
+			Self::DownloadVolumeFactor => result.download_volume_factor.map(f64::from),
+			Self::UploadVolumeFactor => result.upload_volume_factor.map(f64::from),
+			Self::MinimumRatio => result.minimum_ratio.map(f64::from)
+		}
+	}
+}
+
+impl fmt::Display for NumericField {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Size => "size",
+			Self::Seeders => "seeders",
+			Self::Leechers => "leechers",
+			Self::Peers => "peers",
+			Self::Grabs => "grabs",
+			Self::Age => "age",
+			Self::DownloadVolumeFactor => "download volume factor",
+			Self::UploadVolumeFactor => "upload volume factor",
+			Self::MinimumRatio => "minimum ratio"
+		})
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+/// How [`Rule::Compare`] compares a field to its value; serialized as the operator, e.g. `">="`.
+pub enum Comparison {
+	#[serde(rename = "<")]
+	Less,
+	#[serde(rename = "<=")]
+	LessOrEqual,
+	#[serde(rename = "==")]
+	Equal,
+	#[serde(rename = "!=")]
+	NotEqual,
+	#[serde(rename = ">=")]
+	GreaterOrEqual,
+	#[serde(rename = ">")]
+	Greater
+}
+
+impl Comparison {
+	fn holds(self, a: f64, b: f64) -> bool {
+		match self {
+			Self::Less => a < b,
+			Self::LessOrEqual => a <= b,
+			Self::Equal => a == b,
+			Self::NotEqual => a != b,
+			Self::GreaterOrEqual => a >= b,
+			Self::Greater => a > b
+		}
+	}
+}
+
+impl fmt::Display for Comparison {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Less => "<",
+			Self::LessOrEqual => "<=",
+			Self::Equal => "==",
+			Self::NotEqual => "!=",
+			Self::GreaterOrEqual => ">=",
+			Self::Greater => ">"
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+/// Why a [`Filter`] rejected a result.
+pub struct Rejection {
+	/// The innermost [named](Rule::Named) rule the failing rule was part of, if any.
+	pub rule: Option<String>,
+	/// What the failing rule requires, and what the result had instead.
+	pub reason: String
+}
+
+impl fmt::Display for Rejection {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(rule) = &self.rule {
+			write!(f, "{}: ", rule)?;
+		}
+		f.write_str(&self.reason)
+	}
+}
+
+impl Rule {
+	/// Checks `result` against this rule, taking the current time to be `now`.
+	pub fn check_at(&self, result: &SearchResult, now: DateTime<Utc>) -> Result<(), Rejection> {
+		let fail = |actual: String| Err(Rejection{rule: None, reason: format!("requires {}, but {}", self, actual)});
+		match self {
+			Self::All(rules) => rules.iter().try_for_each(|rule| rule.check_at(result, now)),
+			Self::Any(rules) => {
+				let mut reasons = Vec::with_capacity(rules.len());
+				for rule in rules {
+					match rule.check_at(result, now) {
+						Ok(()) => return Ok(()),
+						Err(rejection) => reasons.push(rejection.to_string())
+					}
+				}
+				fail(format!("none of them passed ({})", reasons.join("; ")))
+			},
+			Self::Not(rule) => match (rule.check_at(result, now), &**rule) {
+				(Ok(()), Self::Named{name, ..}) => Err(Rejection{rule: Some(name.clone()), reason: format!("requires {}, but it passed", self)}),
+				(Ok(()), _) => fail("it passed".to_string()),
+				(Err(_), _) => Ok(())
+			},
+			Self::Named{name, rule} => rule.check_at(result, now).map_err(|mut rejection| {
+				rejection.rule.get_or_insert_with(|| name.clone());
+				rejection
+			}),
+			Self::Matches{field, regex} => match field.get(result) {
+				Some(value) if(regex.is_match(value)) => Ok(()),
+				Some(value) => fail(format!("the {} is {:?}", field, value)),
+				None => fail(format!("there's no {}", field))
+			},
+			Self::Compare{field, op, value} => match field.get(result, now) {
+				Some(actual) if(op.holds(actual, *value)) => Ok(()),
+				Some(actual) => fail(format!("{} is {}", field, actual)),
+				None => fail(format!("{} is unknown", field))
+			},
+			Self::Size{categories, min, max} => {
+				let applies = categories.is_empty() || result.categories.iter().any(|category| categories.iter().any(|c| c == category || *c == category - category % 1000));
+				let fits = min.map(|min| result.size >= min).unwrap_or(true) && max.map(|max| result.size <= max).unwrap_or(true);
+				match (!applies || fits) {
+					true => Ok(()),
+					false => fail(format!("it's {} bytes", result.size))
+				}
+			},
+			Self::MinSeeders(min) => match result.seeders {
+				Some(seeders) if(seeders >= *min) => Ok(()),
+				Some(seeders) => fail(format!("it has {}", seeders)),
+				None => fail("the number of seeders is unknown".to_string())
+			},
+			Self::RequiredWords(words) => match words.iter().find(|word| !contains_word(&result.name, word)) {
+				None => Ok(()),
+				Some(word) => fail(format!("`{}` is missing", word))
+			},
+			Self::ExcludedWords(words) => match words.iter().find(|word| contains_word(&result.name, word)) {
+				None => Ok(()),
+				Some(word) => fail(format!("it contains `{}`", word))
+			},
+			Self::ReleaseGroups(groups) => match release_group(&result.name) {
+				Some(group) if(groups.iter().any(|g| g.eq_ignore_ascii_case(group))) => Ok(()),
+				Some(group) => fail(format!("the group is `{}`", group)),
+				None => fail("it has no release group".to_string())
+			},
+			Self::MaxAge(max_age) => match result.age_at(now) {
+				Some(age) if(age <= *max_age) => Ok(()),
+				Some(age) => fail(format!("it's {} old", duration(Duration::from_secs(age.as_secs() / 60 * 60)))),
+				None => fail("its age is unknown".to_string())
+			},
+			Self::Freeleech => match result.download_volume_factor {
+				_ if(result.is_freeleech()) => Ok(()),
+				Some(factor) => fail(format!("its download volume factor is {}", factor)),
+				None => fail("it has no download volume factor".to_string())
+			},
+			Self::Resolutions(resolutions) => match resolution(&result.name) {
+				Some(found) if(resolutions.iter().any(|r| normalize_resolution(r) == found)) => Ok(()),
+				Some(found) => fail(format!("it's {}", found)),
+				None => fail("its resolution is unknown".to_string())
+			}
+		}
+	}
+}
+
+impl fmt::Display for Rule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let list = |f: &mut fmt::Formatter<'_>, items: &mut dyn Iterator<Item = String>| f.write_str(&items.collect::<Vec<_>>().join(", "));
+		match self {
+			Self::All(rules) => {
+				f.write_str("all of (")?;
+				list(f, &mut rules.iter().map(Rule::to_string))?;
+				f.write_str(")")
+			},
+			Self::Any(rules) => {
+				f.write_str("any of (")?;
+				list(f, &mut rules.iter().map(Rule::to_string))?;
+				f.write_str(")")
+			},
+			Self::Not(rule) => write!(f, "not {}", rule),
+			Self::Named{name, ..} => write!(f, "{:?}", name),
+			Self::Matches{field, regex} => write!(f, "the {} to match /{}/", field, regex),
+			Self::Compare{field, op, value} => write!(f, "{} {} {}", field, op, value),
+			Self::Size{categories, min, max} => {
+				match (min, max) {
+					(Some(min), Some(max)) => write!(f, "a size of {} to {} bytes", min, max)?,
+					(Some(min), None) => write!(f, "a size of at least {} bytes", min)?,
+					(None, Some(max)) => write!(f, "a size of at most {} bytes", max)?,
+					(None, None) => f.write_str("any size")?
+				}
+				if(!categories.is_empty()) {
+					f.write_str(" in categories ")?;
+					list(f, &mut categories.iter().map(u32::to_string))?;
+				}
+				Ok(())
+			},
+			Self::MinSeeders(min) => write!(f, "at least {} seeders", min),
+			Self::RequiredWords(words) => {
+				f.write_str("all of the words ")?;
+				list(f, &mut words.iter().map(|word| format!("`{}`", word)))
+			},
+			Self::ExcludedWords(words) => {
+				f.write_str("none of the words ")?;
+				list(f, &mut words.iter().map(|word| format!("`{}`", word)))
+			},
+			Self::ReleaseGroups(groups) => {
+				f.write_str("a release group of ")?;
+				list(f, &mut groups.iter().map(|group| format!("`{}`", group)))
+			},
+			Self::MaxAge(max_age) => write!(f, "an age of at most {}", duration(*max_age)),
+			Self::Freeleech => f.write_str("freeleech"),
+			Self::Resolutions(resolutions) => {
+				f.write_str("a resolution of ")?;
+				list(f, &mut resolutions.iter().cloned())
+			}
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+/// A list of [`Rule`]s, all of which a result must pass.
+pub struct Filter {
+	pub rules: Vec<Rule>
+}
+
+impl Filter {
+	pub fn new(rules: Vec<Rule>) -> Self {
+		Self{rules}
+	}
+
+	/// Checks `result` against each rule in turn, returning the first rejection.
+	pub fn check(&self, result: &SearchResult) -> Result<(), Rejection> {
+		self.check_at(result, Utc::now())
+	}
+
+	/// Like [`check()`](Self::check), but relative to `now` rather than the current time.
+	pub fn check_at(&self, result: &SearchResult, now: DateTime<Utc>) -> Result<(), Rejection> {
+		self.rules.iter().try_for_each(|rule| rule.check_at(result, now))
+	}
+
+	/// Splits `results` into those that pass and those that don't, each with the reason it was rejected.
+	pub fn partition(&self, results: impl IntoIterator<Item = SearchResult>) -> (Vec<SearchResult>, Vec<(SearchResult, Rejection)>) {
+		let now = Utc::now();
+		let mut accepted = Vec::new();
+		let mut rejected = Vec::new();
+		for result in results {
+			match self.check_at(&result, now) {
+				Ok(()) => accepted.push(result),
+				Err(rejection) => rejected.push((result, rejection))
+			}
+		}
+		(accepted, rejected)
+	}
+}
+
+/// The alphanumeric runs of `text`, lowercased.
+fn tokens(text: &str) -> Vec<String> {
+	text.split(|c: char| !c.is_alphanumeric()).filter(|token| !token.is_empty()).map(str::to_lowercase).collect()
+}
+
+/// Whether `word`'s tokens appear consecutively among `name`'s.
+fn contains_word(name: &str, word: &str) -> bool {
+	let word = tokens(word);
+	!word.is_empty() && tokens(name).windows(word.len()).any(|window| window == word.as_slice())
+}
+
+/// Hyphenated tags that end names with no release group, whose second half would otherwise be taken for one.
+const SOURCE_TAGS: &[&str] = &["WEB-DL", "HD-DVD", "DVD-R", "BD-R", "DTS-HD", "DTS-X", "DD-EX"];
+
+/// The part of `name` after its last `-`, ignoring a trailing `[tag]` or file extension, if it's a single token and
+/// not the end of a source tag such as `WEB-DL`.
+fn release_group(name: &str) -> Option<&str> {
+	let mut name = name.trim();
+	if(name.ends_with(']')) {
+		name = name[..name.rfind('[')?].trim_end();
+	}
+	for extension in [".mkv", ".mp4", ".avi", ".torrent", ".nzb"] {
+		if(name.len() > extension.len() && name.is_char_boundary(name.len() - extension.len()) && name[name.len() - extension.len()..].eq_ignore_ascii_case(extension)) {
+			name = &name[..name.len() - extension.len()];
+		}
+	}
+	let (rest, group) = name.rsplit_once('-')?;
+	let prefix = rest.rsplit(|c: char| !c.is_alphanumeric()).next().unwrap_or(rest);
+	if(group.is_empty() || !group.chars().all(char::is_alphanumeric)) {
+		return None;
+	}
+	match SOURCE_TAGS.iter().any(|tag| tag.eq_ignore_ascii_case(&format!("{}-{}", prefix, group))) {
+		true => None,
+		false => Some(group)
+	}
+}
+
+fn normalize_resolution(resolution: &str) -> String {
+	match resolution.to_lowercase().as_str() {
+		"4k" | "uhd" => "2160p".to_string(),
+		resolution => resolution.to_string()
+	}
+}
+
+/// The first token of `name` that looks like a resolution, e.g. `720p` or `1080i`.
+fn resolution(name: &str) -> Option<String> {
+	tokens(name).into_iter().map(|token| normalize_resolution(&token)).find(|token| {
+		let digits = token.trim_end_matches(['p', 'i']);
+		token.len() == digits.len() + 1 && (3..=4).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit())
+	})
+}
+
+/// `duration` in the largest unit that divides it.
+// `u64::is_multiple_of()` needs a newer compiler than the crate otherwise does.
+#[allow(clippy::manual_is_multiple_of)]
+fn duration(duration: Duration) -> String {
+	let secs = duration.as_secs();
+	let (count, unit) = IntoIterator::into_iter([(86400, "day"), (3600, "hour"), (60, "minute")])
+		.find(|(unit, _)| secs > 0 && secs % *unit == 0)
+		.map(|(size, unit)| (secs / size, unit))
+		.unwrap_or((secs, "second"));
+	format!("{} {}{}", count, unit, if(count == 1) { "" } else { "s" })
+}
+
+mod regex_string {
+	use regex::Regex;
+	use serde::Deserialize;
+	use serde::Deserializer;
+	use serde::Serializer;
+
+	pub(super) fn serialize<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(regex.as_str())
+	}
+
+	pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+		Regex::new(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+	}
+}
+
+mod seconds {
+	use std::time::Duration;
+
+	use serde::Deserialize;
+	use serde::Deserializer;
+	use serde::Serializer;
+
+	pub(super) fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_u64(duration.as_secs())
+	}
+
+	pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+		Ok(Duration::from_secs(u64::deserialize(deserializer)?))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::TimeZone;
+
+	use super::*;
+	use crate::IndexerSource;
+
+	fn result() -> SearchResult {
+		SearchResult{
+			name: "Some.Movie.2021.1080p.WEB-DL.x264-GROUP".to_string(),
+			size: 4_000_000_000,
+			categories: vec![2040],
+			seeders: Some(12),
+			download_volume_factor: Some(1.0),
+			published_at: Some(Utc.with_ymd_and_hms(2021, 6, 1, 0, 0, 0).unwrap().into()),
+			indexer: Some(IndexerSource{id: Some("example".to_string()), name: None}),
+			..SearchResult::default()
+		}
+	}
+
+	fn reason(rule: &Rule) -> Option<String> {
+		let now = Utc.with_ymd_and_hms(2021, 6, 3, 12, 30, 0).unwrap();
+		rule.check_at(&result(), now).err().map(|rejection| rejection.to_string())
+	}
+
+	#[test]
+	fn rules() {
+		let reject = |rule: Rule| reason(&rule).unwrap();
+		assert_eq!(reject(Rule::MinSeeders(20)), "requires at least 20 seeders, but it has 12");
+		assert!(reason(&Rule::MinSeeders(12)).is_none());
+		assert_eq!(reject(Rule::Size{categories: vec![2000], min: None, max: Some(2_000_000_000)}), "requires a size of at most 2000000000 bytes in categories 2000, but it's 4000000000 bytes");
+		assert!(reason(&Rule::Size{categories: vec![5000], min: None, max: Some(1)}).is_none());
+		assert!(reason(&Rule::RequiredWords(vec!["web-dl".to_string(), "X264".to_string()])).is_none());
+		assert_eq!(reject(Rule::RequiredWords(vec!["bluray".to_string()])), "requires all of the words `bluray`, but `bluray` is missing");
+		assert_eq!(reject(Rule::ExcludedWords(vec!["cam".to_string(), "web dl".to_string()])), "requires none of the words `cam`, `web dl`, but it contains `web dl`");
+		assert!(reason(&Rule::ReleaseGroups(vec!["group".to_string()])).is_none());
+		assert_eq!(reject(Rule::ReleaseGroups(vec!["OTHER".to_string()])), "requires a release group of `OTHER`, but the group is `GROUP`");
+		assert_eq!(reject(Rule::MaxAge(Duration::from_secs(86400))), "requires an age of at most 1 day, but it's 3630 minutes old");
+		assert_eq!(reject(Rule::Freeleech), "requires freeleech, but its download volume factor is 1");
+		assert!(reason(&Rule::Resolutions(vec!["720p".to_string(), "1080p".to_string()])).is_none());
+		assert_eq!(reject(Rule::Resolutions(vec!["4k".to_string()])), "requires a resolution of 4k, but it's 1080p");
+		let seeded = Rule::Named{name: "seeded".to_string(), rule: Box::new(Rule::MinSeeders(10))};
+		let rejection = Rule::Not(Box::new(seeded)).check_at(&result(), Utc::now()).unwrap_err();
+		assert_eq!(rejection.rule.as_deref(), Some("seeded"));
+		assert_eq!(rejection.to_string(), r#"seeded: requires not "seeded", but it passed"#);
+		assert_eq!(reject(Rule::Compare{field: NumericField::Grabs, op: Comparison::Greater, value: 0.0}), "requires grabs > 0, but grabs is unknown");
+
+		assert_eq!(release_group("Show.S01E01.720p-GRP [eztv]"), Some("GRP"));
+		assert_eq!(release_group("Show.S01E01.720p-GRP.mkv"), Some("GRP"));
+		assert_eq!(release_group("Movie.2021.1080p.WEB-DL-GRP"), Some("GRP"));
+		assert_eq!(release_group("Movie.2021.1080p.WEB-DL"), None);
+		assert_eq!(release_group("Movie 2021 1080p web-dl.mkv"), None);
+		assert_eq!(release_group("Movie.2021.1080p.BluRay.DTS-HD"), None);
+		let mut ungrouped = result();
+		ungrouped.name = "Some.Movie.2021.1080p.WEB-DL".to_string();
+		assert_eq!(Rule::ReleaseGroups(vec!["DL".to_string()]).check_at(&ungrouped, Utc::now()).unwrap_err().reason, "requires a release group of `DL`, but it has no release group");
+		assert_eq!(resolution("Show 2160p"), Some("2160p".to_string()));
+		assert_eq!(resolution("Show.2021.UHD"), Some("2160p".to_string()));
+		assert_eq!(resolution("Show.2021"), None);
+	}
+
+	#[test]
+	fn from_json() {
+		let filter: Filter = serde_json::from_str(r#"[
+			{"compare": {"field": "size", "op": "<", "value": 1e10}},
+			{"any": ["freeleech", {"compare": {"field": "seeders", "op": ">=", "value": 10}}]},
+			{"named": {"name": "no x265", "rule": {"not": {"matches": {"field": "name", "regex": "(?i)x265|hevc"}}}}},
+			{"max_age": 604800}
+		]"#).unwrap();
+		let now = Utc.with_ymd_and_hms(2021, 6, 3, 0, 0, 0).unwrap();
+		assert_eq!(filter.check_at(&result(), now), Ok(()));
+
+		let mut hevc = result();
+		hevc.name = "Some.Movie.2021.1080p.HEVC-GROUP".to_string();
+		let rejection = filter.check_at(&hevc, now).unwrap_err();
+		assert_eq!(rejection.rule.as_deref(), Some("no x265"));
+		assert_eq!(rejection.to_string(), "no x265: requires not the name to match /(?i)x265|hevc/, but it passed");
+
+		let mut unseeded = result();
+		unseeded.seeders = Some(3);
+		assert_eq!(filter.check_at(&unseeded, now).unwrap_err().reason, "requires any of (freeleech, seeders >= 10), but none of them passed (requires freeleech, but its download volume factor is 1; requires seeders >= 10, but seeders is 3)");
+		unseeded.download_volume_factor = Some(0.0);
+		assert_eq!(filter.check_at(&unseeded, now), Ok(()));
+
+		let (accepted, rejected) = filter.partition(vec![result(), hevc]);
+		assert_eq!((accepted.len(), rejected.len()), (0, 2));
+		assert!(serde_json::from_str::<Filter>(r#"[{"matches": {"field": "name", "regex": "("}}]"#).is_err());
+		assert_eq!(serde_json::to_string(&filter.rules[3]).unwrap(), r#"{"max_age":604800}"#);
+		assert_eq!(Rule::Matches{field: TextField::Indexer, regex: Regex::new("^example$").unwrap()}.check_at(&result(), now), Ok(()));
+	}
+}
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use crate::Error;
+
+/// How many recent request latencies percentiles are computed over.
+const LATENCY_SAMPLES: usize = 256;
+
+/// Prowlarr's back-off periods, indexed by escalation level.
+const PROWLARR_BACKOFF: [u64; 12] = [0, 60, 300, 600, 900, 1800, 3600, 7200, 14400, 28800, 43200, 86400];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// When a [`Client`](crate::Client) stops sending requests to a failing indexer, and for how long.  Failures are
+/// requests that couldn't reach the indexer or got a `5xx` or `429`; other errors don't count either way.
+pub struct HealthPolicy {
+	/// Consecutive failures before the circuit opens.
+	pub failure_threshold: u32,
+	/// How long the circuit stays open at each escalation level.  Each further failure escalates a level, up to the
+	/// last; each success de-escalates one.
+	pub backoff: Vec<Duration>
+}
+
+impl Default for HealthPolicy {
+	/// Opens after 3 consecutive failures, backing off as Prowlarr does:  1 minute, then 5, 10, 15 and 30 minutes, then
+	/// 1, 2, 4, 8, 12 and finally 24 hours.
+	fn default() -> Self {
+		Self{
+			failure_threshold: 3,
+			backoff: PROWLARR_BACKOFF.iter().map(|secs| Duration::from_secs(*secs)).collect()
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A snapshot of an indexer's health, from [`Client::health()`](crate::Client::health).
+pub struct HealthStatus {
+	pub successes: u64,
+	pub failures: u64,
+	pub consecutive_failures: u32,
+	pub escalation_level: usize,
+	pub latency_p50: Option<Duration>,
+	pub latency_p90: Option<Duration>,
+	pub latency_p99: Option<Duration>,
+	pub last_success_at: Option<DateTime<Utc>>,
+	pub last_failure_at: Option<DateTime<Utc>>,
+	pub last_error: Option<String>,
+	/// Requests fail fast with [`Error::CircuitOpen`] until this time.
+	pub disabled_till: Option<DateTime<Utc>>
+}
+
+impl HealthStatus {
+	pub fn is_available(&self) -> bool {
+		self.disabled_till.is_none()
+	}
+}
+
+#[derive(Debug, Default)]
+struct State {
+	successes: u64,
+	failures: u64,
+	consecutive_failures: u32,
+	escalation_level: usize,
+	latencies: VecDeque<Duration>,
+	last_success_at: Option<DateTime<Utc>>,
+	last_failure_at: Option<DateTime<Utc>>,
+	last_error: Option<String>,
+	open_until: Option<Instant>
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Health {
+	policy: HealthPolicy,
+	state: Mutex<State>
+}
+
+impl Health {
+	pub fn new(policy: HealthPolicy) -> Self {
+		Self{policy, state: Default::default()}
+	}
+
+	/// Fails with [`Error::CircuitOpen`] if requests shouldn't be sent as of `now`.
+	pub fn check(&self, now: Instant) -> Result<(), Error> {
+		match self.state.lock().unwrap().open_until {
+			Some(open_until) if(open_until > now) => Err(Error::CircuitOpen{retry_in: open_until - now}),
+			_ => Ok(())
+		}
+	}
+
+	pub fn record_success(&self, latency: Duration) {
+		let mut state = self.state.lock().unwrap();
+		state.successes += 1;
+		state.consecutive_failures = 0;
+		state.escalation_level = state.escalation_level.saturating_sub(1);
+		state.open_until = None;
+		state.last_success_at = Some(Utc::now());
+		state.push_latency(latency);
+	}
+
+	/// Returns how long the circuit opened for, if it did.
+	pub fn record_failure(&self, err: &Error, latency: Duration, now: Instant) -> Option<Duration> {
+		let mut state = self.state.lock().unwrap();
+		state.failures += 1;
+		state.consecutive_failures += 1;
+		state.last_failure_at = Some(Utc::now());
+		state.last_error = Some(chain(err));
+		state.push_latency(latency);
+		if(state.consecutive_failures >= self.policy.failure_threshold && !self.policy.backoff.is_empty()) {
+			state.escalation_level = (state.escalation_level + 1).min(self.policy.backoff.len() - 1);
+			let backoff = self.policy.backoff[state.escalation_level];
+			state.open_until = Some(now + backoff);
+			return Some(backoff);
+		}
+		None
+	}
+
+	pub fn status(&self) -> HealthStatus {
+		let state = self.state.lock().unwrap();
+		let mut latencies: Vec<_> = state.latencies.iter().copied().collect();
+		latencies.sort_unstable();
+		let percentile = |p: usize| latencies.get((latencies.len() * p / 100).min(latencies.len().saturating_sub(1))).copied();
+		let now = Instant::now();
+		HealthStatus{
+			successes: state.successes,
+			failures: state.failures,
+			consecutive_failures: state.consecutive_failures,
+			escalation_level: state.escalation_level,
+			latency_p50: percentile(50),
+			latency_p90: percentile(90),
+			latency_p99: percentile(99),
+			last_success_at: state.last_success_at,
+			last_failure_at: state.last_failure_at,
+			last_error: state.last_error.clone(),
+			disabled_till: state.open_until
+				.filter(|open_until| *open_until > now)
+				.and_then(|open_until| chrono::Duration::from_std(open_until - now).ok())
+				.map(|remaining| Utc::now() + remaining)
+		}
+	}
+
+	pub fn reset(&self) {
+		*self.state.lock().unwrap() = State::default();
+	}
+}
+
+/// `err` and its sources, e.g. `HTTP error: connection failed: Connection refused`.
+fn chain(err: &Error) -> String {
+	let mut message = err.to_string();
+	let mut source = std::error::Error::source(err);
+	while let Some(err) = source {
+		message.push_str(": ");
+		message.push_str(&err.to_string());
+		source = err.source();
+	}
+	message
+}
+
+impl State {
+	fn push_latency(&mut self, latency: Duration) {
+		if(self.latencies.len() == LATENCY_SAMPLES) {
+			self.latencies.pop_front();
+		}
+		self.latencies.push_back(latency);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::transport::TransportError;
+	use crate::transport::TransportErrorKind;
+
+	#[test]
+	fn escalating_backoff() {
+		let health = Health::new(HealthPolicy{failure_threshold: 2, ..HealthPolicy::default()});
+		let start = Instant::now();
+		let err = Error::Status(503);
+		assert_eq!(health.record_failure(&err, Duration::from_millis(30), start), None);
+		health.check(start).unwrap();
+		assert_eq!(health.record_failure(&err, Duration::from_millis(10), start), Some(Duration::from_secs(60)));
+		assert!(matches!(health.check(start), Err(Error::CircuitOpen{retry_in}) if retry_in == Duration::from_secs(60)));
+		health.check(start + Duration::from_secs(60)).unwrap();
+		health.record_failure(&err, Duration::from_millis(20), start + Duration::from_secs(60));
+		assert!(matches!(health.check(start + Duration::from_secs(60)), Err(Error::CircuitOpen{retry_in}) if retry_in == Duration::from_secs(300)));
+
+		let status = health.status();
+		assert_eq!((status.failures, status.consecutive_failures, status.escalation_level), (3, 3, 2));
+		assert_eq!(status.latency_p50, Some(Duration::from_millis(20)));
+		assert_eq!(status.last_error.as_deref(), Some("HTTP status 503"));
+		assert!(!status.is_available());
+
+		health.record_success(Duration::from_millis(40));
+		health.check(start).unwrap();
+		let status = health.status();
+		assert_eq!((status.successes, status.consecutive_failures, status.escalation_level), (1, 0, 1));
+		assert!(status.is_available());
+	}
+
+	#[test]
+	fn failures() {
+		let refused = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "connection refused");
+		let err = Error::Transport(TransportError::new(TransportErrorKind::Connect, refused));
+		assert!(err.is_unavailable() && Error::Status(429).is_unavailable() && Error::Status(502).is_unavailable());
+		assert!(!Error::Status(404).is_unavailable() && !Error::NotAFeed.is_unavailable() && !Error::Indexer{code: Some(100), description: String::new()}.is_unavailable());
+
+		let health = Health::default();
+		health.record_failure(&err, Duration::from_millis(10), Instant::now());
+		assert_eq!(health.status().last_error.as_deref(), Some("HTTP error: connection failed: connection refused"));
+	}
+}
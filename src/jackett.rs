@@ -0,0 +1,417 @@
+use std::fmt;
+use std::io::BufRead;
+use std::sync::Arc;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use smartcow::SmartCow;
+use tracing::instrument;
+use url::Url;
+
+use crate::caps::indexer_error;
+use crate::caps::read_caps;
+use crate::auth::Refresh;
+use crate::endpoint;
+use crate::xml::attr;
+use crate::xml::element_text;
+use crate::xml::skip;
+use crate::Caps;
+use crate::Client;
+use crate::CookieJar;
+use crate::Credentials;
+use crate::Error;
+use crate::Limits;
+use crate::Privacy;
+use crate::transport;
+use crate::Transport;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexerStatus {
+	Healthy,
+	Failing,
+	Unknown
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Jackett's indexer filter syntax, usable in place of an indexer ID to address several indexers at once.  Jackett only
+/// understands an OR (`,`) of ANDs (`+`) of single filters, each possibly negated (`!`), so other nesting is rewritten
+/// into an equivalent of that form when formatted.
+pub enum Filter {
+	All,
+	Privacy(Privacy),
+	Tag(String),
+	Language(String),
+	/// Whether the indexer's last test passed.
+	Test(bool),
+	Status(IndexerStatus),
+	Not(Box<Filter>),
+	And(Vec<Filter>),
+	Or(Vec<Filter>)
+}
+
+/// A [`Filter`] that's neither `Not`, `And` nor `Or`, as [`Filter::disjunction()`] breaks filters down into.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Leaf<'a> {
+	All,
+	Privacy(Privacy),
+	Tag(&'a str),
+	Language(&'a str),
+	Test(bool),
+	Status(IndexerStatus)
+}
+
+impl fmt::Display for Leaf<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::All => f.write_str("all"),
+			Self::Privacy(Privacy::Public) => f.write_str("type:public"),
+			Self::Privacy(Privacy::SemiPrivate) => f.write_str("type:semi-private"),
+			Self::Privacy(Privacy::Private) => f.write_str("type:private"),
+			Self::Tag(tag) => write!(f, "tag:{}", tag),
+			Self::Language(language) => write!(f, "lang:{}", language),
+			Self::Test(passed) => f.write_str(if(*passed) { "test:passed" } else { "test:failed" }),
+			Self::Status(IndexerStatus::Healthy) => f.write_str("status:healthy"),
+			Self::Status(IndexerStatus::Failing) => f.write_str("status:failing"),
+			Self::Status(IndexerStatus::Unknown) => f.write_str("status:unknown")
+		}
+	}
+}
+
+/// Characters with a meaning in Jackett's filter syntax, which tags and languages can't contain.
+const RESERVED: &[char] = &[',', '+', '!', ':'];
+
+impl Filter {
+	/// The filter as an OR of ANDs of possibly negated single filters, which is all Jackett's syntax can express.
+	/// `Not` is pushed inwards by De Morgan's laws, and `And` distributed over `Or`.
+	fn disjunction(&self, negated: bool) -> Vec<Vec<(bool, Leaf<'_>)>> {
+		let leaf = match (self, negated) {
+			(Self::Not(filter), _) => return filter.disjunction(!negated),
+			(Self::And(filters), false) | (Self::Or(filters), true) => return filters.iter().fold(vec![vec![]], |terms, filter| {
+				let disjunction = filter.disjunction(negated);
+				terms.iter().flat_map(|term| disjunction.iter().map(move |other| term.iter().chain(other).copied().collect())).collect()
+			}),
+			(Self::Or(filters), false) | (Self::And(filters), true) => return filters.iter().flat_map(|filter| filter.disjunction(negated)).collect(),
+			(Self::All, _) => Leaf::All,
+			(Self::Privacy(privacy), _) => Leaf::Privacy(*privacy),
+			(Self::Tag(tag), _) => Leaf::Tag(tag),
+			(Self::Language(language), _) => Leaf::Language(language),
+			(Self::Test(passed), _) => Leaf::Test(*passed),
+			(Self::Status(status), _) => Leaf::Status(*status)
+		};
+		vec![vec![(negated, leaf)]]
+	}
+
+	/// The filter in Jackett's syntax, for use in place of an indexer ID, failing with [`Error::InvalidFilter`] if
+	/// Jackett can't express it:  if it can match nothing, e.g. an empty `Or` or a negated `All`, or if a tag or
+	/// language contains one of `,+!:`.
+	pub fn to_id(&self) -> Result<String, Error> {
+		let disjunction = self.disjunction(false);
+		if(disjunction.is_empty()) {
+			return Err(Error::InvalidFilter("matches no indexers".to_string()));
+		}
+		for (negated, leaf) in disjunction.iter().flatten() {
+			match leaf {
+				Leaf::All if(*negated) => return Err(Error::InvalidFilter("negated `all` matches no indexers".to_string())),
+				Leaf::Tag(value) | Leaf::Language(value) if(value.is_empty() || value.contains(RESERVED)) => return Err(Error::InvalidFilter(format!("`{}` is empty or contains one of `,+!:`", leaf))),
+				_ => {}
+			}
+		}
+		Ok(self.to_string())
+	}
+}
+
+/// Writes the filter as is, even if Jackett can't make sense of it; [`Filter::to_id()`] checks it can.
+impl fmt::Display for Filter {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (i, term) in self.disjunction(false).iter().enumerate() {
+			if(i > 0) {
+				f.write_str(",")?;
+			}
+			let mut leaves = term.iter().filter(|(negated, leaf)| *negated || *leaf != Leaf::All).peekable();
+			if(leaves.peek().is_none()) {
+				f.write_str("all")?;
+			}
+			for (j, (negated, leaf)) in leaves.enumerate() {
+				if(j > 0) {
+					f.write_str("+")?;
+				}
+				if(*negated) {
+					f.write_str("!")?;
+				}
+				write!(f, "{}", leaf)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// An indexer as listed by Jackett's `t=indexers`.
+pub struct JackettIndexer {
+	pub id: String,
+	pub configured: bool,
+	pub title: Option<String>,
+	pub description: Option<String>,
+	pub link: Option<String>,
+	pub language: Option<String>,
+	pub privacy: Option<Privacy>,
+	pub caps: Option<Caps>
+}
+
+#[derive(Clone)]
+/// A Jackett instance, for discovering its indexers and building a [`Client`] for each.
+pub struct Jackett {
+	base_url: Url,
+	/// A client for the instance itself, whose transport, credentials and cookies are shared with every indexer's.
+	root: Client
+}
+
+impl Jackett {
+	/// `base_url` is the root of the Jackett instance, e.g. `http://localhost:9117`, or with the `unix-socket` feature,
+	/// `unix:/run/jackett/jackett.sock`.
+	#[instrument(err, level = "info", skip(base_url, apikey), fields(base_url = %endpoint::redacted(&base_url.to_string())))]
+	#[cfg(feature = "reqwest")]
+	pub fn new(base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		let (transport, base_url) = crate::transport_for(&base_url.to_string())?;
+		Self::from_parts(transport, base_url, apikey)
+	}
+
+	/// Like [`new()`](Self::new), but sends requests through `transport`.
+	pub fn with_transport(base_url: impl ToString, apikey: impl ToString, transport: impl Transport + 'static) -> Result<Self, Error> {
+		Self::from_parts(Arc::new(transport), base_url, apikey)
+	}
+
+	fn from_parts(transport: Arc<dyn Transport>, base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		let base_url = endpoint::parse_base(&base_url.to_string())?;
+		let root = Client::from_parts(transport, base_url.clone(), apikey);
+		Ok(Self{base_url, root})
+	}
+
+	/// Replaces the instance's credentials, including the API key it was created with; see [`Credentials`].  They're
+	/// used for discovery and passed on to every indexer's client.
+	pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+		self.root = self.root.with_credentials(credentials);
+		self
+	}
+
+	/// Sets the bounds on what's accepted from the instance, for discovery and every indexer's client; see [`Limits`].
+	pub fn with_limits(mut self, limits: Limits) -> Self {
+		self.root = self.root.with_limits(limits);
+		self
+	}
+
+	/// Keeps cookies in `jar`, for discovery and every indexer's client.
+	pub fn with_cookie_jar(mut self, jar: CookieJar) -> Self {
+		self.root = self.root.with_cookie_jar(jar);
+		self
+	}
+
+	/// Calls `refresh` for new credentials when the instance answers `401 Unauthorized`; see [`Refresh`].
+	pub fn with_credential_refresh(mut self, refresh: impl Refresh + 'static) -> Self {
+		self.root = self.root.with_credential_refresh(refresh);
+		self
+	}
+
+	fn endpoint(&self, indexer: &str) -> Url {
+		endpoint::join(&self.base_url, &["api", "v2.0", "indexers", indexer, "results", "torznab", "api"])
+	}
+
+	/// A [`Client`] for a single indexer, or for every indexer matching a [`Filter`] (pass its
+	/// [`to_id()`](Filter::to_id)).
+	pub fn client(&self, indexer: &str) -> Client {
+		self.root.for_endpoint(self.endpoint(indexer)).with_name(indexer)
+	}
+
+	/// Lists indexers matching `filter`.  `configured` restricts the list to configured or unconfigured indexers.
+	#[instrument(err, level = "info", skip(self))]
+	pub async fn indexers(&self, filter: &Filter, configured: Option<bool>) -> Result<Vec<JackettIndexer>, Error> {
+		let client = self.client(&filter.to_id()?);
+		let configured = configured.map(|configured| configured.to_string());
+		let mut qparams = vec![("t", SmartCow::Borrowed("indexers"))];
+		qparams.extend(configured.as_deref().map(|configured| ("configured", SmartCow::Borrowed(configured))));
+		let bytes = transport::success(client.fetch(|credentials| client.url(credentials, &qparams)).await?)?.body;
+		parse_indexers(&bytes)
+	}
+
+	/// Lists configured indexers matching `filter`, each with a ready [`Client`] primed with the indexer's caps.
+	pub async fn clients(&self, filter: &Filter) -> Result<Vec<(JackettIndexer, Client)>, Error> {
+		Ok(self.indexers(filter, Some(true)).await?.into_iter().map(|indexer| {
+			let mut client = self.client(&indexer.id);
+			if let Some(caps) = &indexer.caps {
+				client = client.with_caps(caps.clone());
+			}
+			(indexer, client)
+		}).collect())
+	}
+}
+
+fn read_indexer<R: BufRead>(reader: &mut Reader<R>, mut indexer: JackettIndexer) -> Result<JackettIndexer, Error> {
+	let mut buf = Vec::new();
+	loop {
+		match reader.read_event_into(&mut buf)? {
+			Event::Start(element) => match element.local_name().as_ref() {
+				b"title" => indexer.title = element_text(reader)?,
+				b"description" => indexer.description = element_text(reader)?,
+				b"link" => indexer.link = element_text(reader)?,
+				b"language" => indexer.language = element_text(reader)?,
+				b"type" => indexer.privacy = element_text(reader)?.and_then(|privacy| privacy.parse().ok()),
+				b"caps" => indexer.caps = Some(read_caps(reader)?),
+				_ => skip(reader, &element)?
+			},
+			Event::End(_) | Event::Eof => break,
+			_ => {}
+		}
+		buf.clear();
+	}
+	Ok(indexer)
+}
+
+fn parse_indexers(bytes: &[u8]) -> Result<Vec<JackettIndexer>, Error> {
+	let mut reader = Reader::from_reader(bytes);
+	reader.config_mut().expand_empty_elements = true;
+	let mut indexers = Vec::new();
+	let mut buf = Vec::new();
+	loop {
+		match reader.read_event_into(&mut buf)? {
+			Event::Start(element) => match element.local_name().as_ref() {
+				b"indexers" => {},
+				b"indexer" => {
+					let indexer = JackettIndexer{
+						id: attr(&reader, &element, b"id")?.ok_or_else(|| Error::MissingField("id".to_string()))?,
+						configured: attr(&reader, &element, b"configured")?.map(|v| v == "true").unwrap_or(false),
+						..JackettIndexer::default()
+					};
+					indexers.push(read_indexer(&mut reader, indexer)?);
+				},
+				b"error" => return Err(indexer_error(&reader, &element)?),
+				_ => skip(&mut reader, &element)?
+			},
+			Event::Eof => break,
+			_ => {}
+		}
+		buf.clear();
+	}
+	Ok(indexers)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::transport::from_fn;
+	use crate::transport::TransportErrorKind;
+	use crate::SearchMode;
+
+	fn unreachable() -> impl Transport {
+		from_fn(|_| Err(TransportErrorKind::Connect.into()))
+	}
+
+	#[test]
+	fn filter_syntax() {
+		let filter = Filter::And(vec![
+			Filter::Status(IndexerStatus::Healthy),
+			Filter::Or(vec![Filter::Privacy(Privacy::Private), Filter::Tag("anime fans".to_string())]),
+			Filter::Not(Box::new(Filter::Language("ru-RU".to_string())))
+		]);
+		assert_eq!(filter.to_string(), "status:healthy+type:private+!lang:ru-RU,status:healthy+tag:anime fans+!lang:ru-RU");
+		let jackett = Jackett::with_transport("http://localhost:9117/jackett/", "key", unreachable()).unwrap();
+		assert_eq!(jackett.endpoint(&filter.to_string()).as_str(), "http://localhost:9117/jackett/api/v2.0/indexers/status:healthy+type:private+!lang:ru-RU,status:healthy+tag:anime%20fans+!lang:ru-RU/results/torznab/api");
+
+		let (a, b, c) = (Filter::Tag("a".to_string()), Filter::Tag("b".to_string()), Filter::Tag("c".to_string()));
+		let not = |filter: Filter| Filter::Not(Box::new(filter));
+		assert_eq!(Filter::Or(vec![Filter::And(vec![a.clone(), b.clone()]), c.clone()]).to_string(), "tag:a+tag:b,tag:c");
+		assert_eq!(not(Filter::And(vec![a.clone(), b.clone()])).to_string(), "!tag:a,!tag:b");
+		assert_eq!(not(Filter::Or(vec![a.clone(), not(b.clone())])).to_string(), "!tag:a+tag:b");
+		assert_eq!(Filter::And(vec![Filter::Or(vec![a.clone(), b.clone()]), not(Filter::Or(vec![c.clone(), Filter::Test(false)]))]).to_string(), "tag:a+!tag:c+!test:failed,tag:b+!tag:c+!test:failed");
+		assert_eq!(Filter::And(vec![Filter::All, a.clone()]).to_string(), "tag:a");
+		assert_eq!(Filter::And(vec![]).to_string(), "all");
+		assert_eq!(filter.to_id().unwrap(), filter.to_string());
+
+		for invalid in [
+			Filter::Or(vec![]),
+			not(Filter::And(vec![])),
+			not(Filter::All),
+			Filter::Or(vec![a.clone(), Filter::And(vec![b.clone(), not(Filter::All)])]),
+			Filter::Tag("a,b".to_string()),
+			Filter::Tag("".to_string()),
+			not(Filter::Language("en+US".to_string())),
+			Filter::And(vec![a.clone(), Filter::Tag("!b".to_string())]),
+			Filter::Tag("tag:c".to_string())
+		] {
+			assert!(matches!(invalid.to_id(), Err(Error::InvalidFilter(_))), "{:?}", invalid);
+		}
+	}
+
+	#[tokio::test]
+	async fn invalid_filter() {
+		let jackett = Jackett::with_transport("http://localhost:9117/", "key", from_fn(|request| panic!("requested {}", request.url))).unwrap();
+		assert!(matches!(jackett.indexers(&Filter::Or(vec![]), None).await, Err(Error::InvalidFilter(_))));
+		assert!(matches!(jackett.clients(&Filter::Not(Box::new(Filter::All))).await, Err(Error::InvalidFilter(_))));
+	}
+
+	#[test]
+	fn indexers() {
+		let indexers = parse_indexers(br#"<?xml version="1.0" encoding="UTF-8"?>
+			<indexers>
+			  <indexer id="1337x" configured="true">
+				<title>1337x</title>
+				<description>1337X is a Public torrent site</description>
+				<link>https://1337x.to/</link>
+				<language>en-US</language>
+				<type>public</type>
+				<caps>
+				  <server title="Jackett" />
+				  <searching>
+					<search available="yes" supportedParams="q" />
+					<tv-search available="yes" supportedParams="q,season,ep" />
+				  </searching>
+				  <categories>
+					<category id="5000" name="TV" />
+				  </categories>
+				</caps>
+			  </indexer>
+			  <indexer id="beyond-hd" configured="false">
+				<title>Beyond-HD</title>
+				<type>private</type>
+			  </indexer>
+			</indexers>
+		"#).unwrap();
+		assert_eq!(indexers.len(), 2);
+		assert_eq!(indexers[0].id, "1337x");
+		assert!(indexers[0].configured);
+		assert_eq!(indexers[0].privacy, Some(Privacy::Public));
+		assert!(indexers[0].caps.as_ref().unwrap().supports(SearchMode::TvSearch, "season"));
+		assert!(!indexers[1].configured);
+		assert_eq!(indexers[1].privacy, Some(Privacy::Private));
+
+		let jackett = Jackett::with_transport("http://localhost:9117/", "key", unreachable()).unwrap();
+		assert_eq!(jackett.endpoint("1337x").as_str(), "http://localhost:9117/api/v2.0/indexers/1337x/results/torznab/api");
+	}
+
+	#[tokio::test]
+	async fn credentials() {
+		use crate::transport::Response;
+
+		// As behind a reverse proxy requiring Basic auth.
+		let jackett = Jackett::with_transport("http://localhost:9117/", "", from_fn(|request| {
+			assert_eq!(request.headers, [("X-Api-Key".to_string(), "key".to_string()), ("Authorization".to_string(), "Basic dXNlcjpwYXNz".to_string())]);
+			match request.url.query_pairs().find(|(k, _)| k == "t").map(|(_, t)| t.into_owned()).as_deref() {
+				Some("indexers") => Ok(Response::new(200, r#"<indexers><indexer id="1337x" configured="true" /></indexers>"#)),
+				_ => Ok(Response::new(200, "<caps />"))
+			}
+		})).unwrap().with_credentials(Credentials::new("key").apikey_header("X-Api-Key").basic_auth("user", "pass"));
+		let clients = jackett.clients(&Filter::All).await.unwrap();
+		assert_eq!(clients[0].0.id, "1337x");
+		clients[0].1.caps().await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn body_limit() {
+		let jackett = Jackett::with_transport("http://localhost:9117/", "key", from_fn(|request| {
+			Ok(crate::transport::Response::new(200, vec![b' '; request.max_body_size.unwrap() + 1]))
+		})).unwrap();
+		assert!(matches!(jackett.indexers(&Filter::All, None).await, Err(Error::BodyTooLarge{limit}) if limit == Limits::default().max_body_size));
+		let jackett = jackett.with_limits(Limits{max_body_size: 16, ..Limits::default()});
+		assert!(matches!(jackett.indexers(&Filter::All, None).await, Err(Error::BodyTooLarge{limit: 16})));
+		assert!(matches!(jackett.client("1337x").caps().await, Err(Error::BodyTooLarge{limit: 16})));
+	}
+}
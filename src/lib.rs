@@ -1,108 +1,444 @@
+//! An async client for the Torznab protocol, and a parser for its feeds.
+//!
+//! # Features
+//!
+//! - `reqwest` (default):  [`transport::ReqwestTransport`], used by [`Client::new()`] and friends.
+//! - `rustls` (default), `native-tls`:  the TLS library [`transport::ReqwestTransport`] uses, and its TLS options.
+//!   Certificate pinning needs rustls, which is preferred if both are enabled.  With neither, only plain HTTP works.
+//! - `blocking`:  `blocking::Client`, a synchronous client.
+//! - `brotli`, `deflate`, `zstd`:  accept responses compressed with these, as well as gzip.
+//! - `unix-socket`:  `transport::UnixTransport` and `unix:` base URLs, for indexers on a Unix domain socket.
+//! - `metrics`:  records indexer traffic through the `metrics` facade.
+//! - `parse-names`, `require-parse-names`:  parse release names into `Metadata`.
+//!
+//! With `default-features = false` and none of the above, the crate is just [`parse_feed()`], [`FeedReader`], the
+//! result model and [`Filter`], with no HTTP client, TLS library or async runtime.
 #![allow(unused_parens)]
 extern crate bytes;
-extern crate rss;
 extern crate thiserror;
-extern crate urlencoding;
 
-use bytes::Buf;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Instant;
+
 use bytes::Bytes;
-use itertools::Itertools;
-use rss::Channel;
-use rss::Item;
 use smartcow::SmartCow;
-use smartstring::alias::String;
 use tracing::instrument;
+use url::Url;
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod auth;
+pub use auth::{CookieJar, Credentials};
+mod caps;
+pub use caps::{Caps, CapsCategory, SearchCap, SearchMode};
+mod charset;
+mod compliance;
+pub use compliance::{Compliance, SeedRequirements};
+mod date;
+mod download;
+mod endpoint;
+pub use download::Download;
 mod error;
 pub use error::Error;
+mod feed;
+pub mod filter;
+pub use filter::{Filter, Rejection, Rule};
+mod health;
+pub use health::{HealthPolicy, HealthStatus};
+pub mod jackett;
+pub use jackett::Jackett;
+pub mod nzb;
+pub use nzb::Nzb;
+pub mod prowlarr;
+pub use prowlarr::Prowlarr;
+mod negotiate;
+pub use negotiate::{SearchResponse, Strategy};
+mod parse;
+pub use parse::{parse_feed, parse_feed_reader, ChannelInfo, Feed, Limits, ParseOptions};
+mod query;
+pub use query::Query;
+mod result;
+pub use result::{retain_freeleech, sort_freeleech_first, sort_newest_first, IndexerSource, ParseMode, PasswordStatus, Privacy, Protocol, SearchResult, Usenet, Warning, WarningKind};
+mod stream;
+pub use stream::FeedReader;
+#[cfg(all(test, feature = "reqwest"))]
+mod test_server;
+mod telemetry;
+#[cfg(all(feature = "reqwest", any(feature = "rustls", feature = "native-tls")))]
+mod tls;
 mod torrent;
 mod torznab_cat;
+pub mod transport;
+#[cfg(all(unix, feature = "unix-socket"))]
+mod unix;
+pub use transport::Transport;
+mod xml;
 
 #[cfg(any(feature = "parse-names", feature = "require-parse-names"))]
 /// Re-exported from [`torrent-name-parser`](torrent_name_parser::Metadata)
 pub use torrent_name_parser::Metadata;
 
+/// Re-exported from [`encoding_rs`](encoding_rs::Encoding), for [`Client::with_encoding()`]
+pub use encoding_rs::Encoding;
+
 /// Re-exported from [`torrent-common`](torrent_common::Torrent)
 pub use torrent_common::Torrent;
-use crate::torznab_cat::TorznabCategory;
+pub use crate::torznab_cat::TorznabCategory;
+
+#[cfg(feature = "reqwest")]
+pub(crate) fn default_transport() -> Result<Arc<dyn Transport>, Error> {
+	Ok(Arc::new(transport::ReqwestTransport::new()?))
+}
+
+/// The transport for `base_url`, and the URL to send requests to through it:  for a `unix:` URL (see
+/// [`Client::new()`]), a `UnixTransport` falling back to the default transport for other hosts, and otherwise the
+/// default transport.
+#[cfg(feature = "reqwest")]
+pub(crate) fn transport_for(base_url: &str) -> Result<(Arc<dyn Transport>, String), Error> {
+	#[cfg(all(unix, feature = "unix-socket"))]
+	if let Some(parsed) = unix::parse_url(base_url) {
+		let (socket, base_url) = parsed?;
+		let transport = transport::UnixTransport::new(socket, "localhost").with_fallback_arc(default_transport()?);
+		return Ok((Arc::new(transport), base_url));
+	}
+	Ok((default_transport()?, base_url.to_string()))
+}
 
 #[derive(Clone)]
 pub struct Client {
-	http: reqwest::Client,
-	base_url: std::string::String,
-	apikey: std::string::String
+	transport: Arc<dyn Transport>,
+	base_url: Url,
+	/// Shared between clones, so that credentials refreshed by one are used by all.
+	credentials: Arc<RwLock<Credentials>>,
+	cookies: Option<CookieJar>,
+	refresh: Option<Arc<dyn auth::Refresh>>,
+	/// Identifies the indexer in metrics.
+	name: String,
+	parse_options: ParseOptions,
+	/// Overrides the charset responses declare.
+	encoding: Option<&'static Encoding>,
+	/// Shared between clones, so that caps are fetched once per indexer.
+	caps: Arc<RwLock<Option<Arc<Caps>>>>,
+	/// Likewise shared, so that every clone sees the indexer as down once it is.
+	health: Arc<health::Health>
 }
 
 impl Client {
-	#[instrument(err, level = "info", skip(base_url, apikey), fields(base_url = %base_url.to_string(), apikey = %apikey.to_string()))]
-	pub fn new(base_url: impl ToString, apikey: impl ToString) -> Result<Self, reqwest::Error> {
-		let this = Self{
-			http: reqwest::Client::builder()
-				.gzip(true)
-				.build()?,
-			base_url: base_url.to_string(),
-			apikey: apikey.to_string()
-		};
+	/// `base_url` is the indexer's Torznab endpoint, with or without its final `/api` path segment.  It may carry a
+	/// query string of its own, which is kept on every request.  An empty `apikey` isn't sent, leaving any `apikey` in
+	/// `base_url` in place.
+	///
+	/// With the `unix-socket` feature, `base_url` may instead name a Unix socket and the path on it, as in
+	/// `unix:/run/jackett/jackett.sock:/api/v2.0/indexers/all/results/torznab`.  See
+	/// [`with_unix_socket()`](Self::with_unix_socket).
+	#[cfg(feature = "reqwest")]
+	#[instrument(err, level = "info", skip(base_url, apikey), fields(base_url = %endpoint::redacted(&base_url.to_string())))]
+	pub fn new(base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		let (transport, base_url) = transport_for(&base_url.to_string())?;
+		let this = Self::from_parts(transport, endpoint::parse_api(&base_url)?, apikey);
 		// TODO:  Check caps
 		Ok(this)
 	}
 
+	/// Like [`new()`](Self::new), but sends requests through `transport`.
+	pub fn with_transport(base_url: impl ToString, apikey: impl ToString, transport: impl Transport + 'static) -> Result<Self, Error> {
+		Ok(Self::from_parts(Arc::new(transport), endpoint::parse_api(&base_url.to_string())?, apikey))
+	}
+
+	/// Like [`new()`](Self::new), but connects to the indexer through the Unix socket at `socket`.  The host in
+	/// `base_url` is only a name:  it's sent as the `Host` header, and requests for it go over the socket.  Requests
+	/// for other hosts, e.g. download links to trackers, go over the network, given the `reqwest` feature.
+	#[cfg(all(unix, feature = "unix-socket"))]
+	pub fn with_unix_socket(socket: impl Into<std::path::PathBuf>, base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		let base_url = endpoint::parse_api(&base_url.to_string())?;
+		let transport = transport::UnixTransport::new(socket, base_url.host_str().unwrap_or_default());
+		#[cfg(feature = "reqwest")]
+		let transport = transport.with_fallback_arc(default_transport()?);
+		Ok(Self::from_parts(Arc::new(transport), base_url, apikey))
+	}
+
+	pub(crate) fn from_parts(transport: Arc<dyn Transport>, base_url: Url, apikey: impl ToString) -> Self {
+		let name = format!("{}{}", base_url.host_str().unwrap_or_default(), base_url.path());
+		Self{
+			transport,
+			base_url,
+			credentials: Arc::new(RwLock::new(Credentials::new(apikey))),
+			cookies: None,
+			refresh: None,
+			name,
+			parse_options: Default::default(),
+			encoding: None,
+			caps: Default::default(),
+			health: Default::default()
+		}
+	}
+
+	/// A client for another endpoint of the same server, sharing this one's transport, credentials, cookies, refresh
+	/// hook and parse options, but with caps and health of its own.
+	pub(crate) fn for_endpoint(&self, base_url: Url) -> Self {
+		Self{
+			credentials: self.credentials.clone(),
+			cookies: self.cookies.clone(),
+			refresh: self.refresh.clone(),
+			parse_options: self.parse_options,
+			encoding: self.encoding,
+			..Self::from_parts(self.transport.clone(), base_url, "")
+		}
+	}
+
+	/// Creates a client for a Newznab (Usenet) indexer.  This is [`new()`](Self::new) followed by
+	/// [`with_protocol(Protocol::Usenet)`](Self::with_protocol).
+	#[cfg(feature = "reqwest")]
+	pub fn newznab(base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		Ok(Self::new(base_url, apikey)?.with_protocol(Protocol::Usenet))
+	}
+
+	/// Sets how items that don't fully conform to the Torznab spec are handled; see [`ParseMode`].
+	pub fn with_parse_mode(mut self, parse_mode: ParseMode) -> Self {
+		self.parse_options.mode = parse_mode;
+		self
+	}
+
+	/// Sets the protocol assumed for results that don't indicate their own; defaults to [`Protocol::Torrent`].
+	pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+		self.parse_options.protocol = protocol;
+		self
+	}
+
+	/// Sets the bounds on what's accepted from the indexer; see [`Limits`].
+	pub fn with_limits(mut self, limits: Limits) -> Self {
+		self.parse_options.limits = limits;
+		self
+	}
+
+	/// Sets whether double-encoded titles are repaired; see [`ParseOptions::repair_titles`].
+	pub fn with_title_repair(mut self, repair_titles: bool) -> Self {
+		self.parse_options.repair_titles = repair_titles;
+		self
+	}
+
+	/// Decodes responses as `encoding`, whatever charset they declare.  By default, a response's charset is taken
+	/// from its byte order mark, its `Content-Type` header or its XML declaration, in that order, and it's transcoded
+	/// to UTF-8 before parsing.
+	pub fn with_encoding(mut self, encoding: &'static Encoding) -> Self {
+		self.encoding = Some(encoding);
+		self
+	}
+
+	/// Replaces the client's credentials, including the API key it was created with; see [`Credentials`].
+	pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+		self.credentials = Arc::new(RwLock::new(credentials));
+		self
+	}
+
+	/// Keeps cookies in `jar`, sending them with every request they apply to, including downloads.
+	pub fn with_cookie_jar(mut self, jar: CookieJar) -> Self {
+		self.cookies = Some(jar);
+		self
+	}
+
+	/// Calls `refresh` for new credentials when the indexer answers `401 Unauthorized`; see [`auth::Refresh`].
+	pub fn with_credential_refresh(mut self, refresh: impl auth::Refresh + 'static) -> Self {
+		self.refresh = Some(Arc::new(refresh));
+		self
+	}
+
+	/// Sets the name identifying the indexer in metrics; defaults to the host and path of its URL.
+	pub fn with_name(mut self, name: impl ToString) -> Self {
+		self.name = name.to_string();
+		self
+	}
+
+	/// Sets when the client stops querying a failing indexer; see [`HealthPolicy`].  Resets the indexer's health.
+	pub fn with_health_policy(mut self, policy: HealthPolicy) -> Self {
+		self.health = Arc::new(health::Health::new(policy));
+		self
+	}
+
+	/// A snapshot of the indexer's health, as seen by this client and its clones.
+	pub fn health(&self) -> HealthStatus {
+		self.health.status()
+	}
+
+	/// Forgets past failures, closing the circuit if it's open.
+	pub fn reset_health(&self) {
+		self.health.reset();
+	}
+
+	/// Runs `request` unless the circuit is open, recording its outcome.  Errors count as failures only if they say the
+	/// indexer is unavailable.  `mode` labels it in metrics.
+	async fn tracked<T>(&self, mode: &'static str, request: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+		let start = Instant::now();
+		if let Err(err) = self.health.check(start) {
+			telemetry::request(&self.name, mode, Err(&err), start.elapsed());
+			return Err(err);
+		}
+		let result = request.await;
+		match &result {
+			Ok(_) => self.health.record_success(start.elapsed()),
+			Err(err) if(err.is_unavailable()) => if let Some(backoff) = self.health.record_failure(err, start.elapsed(), Instant::now()) {
+				telemetry::backoff(&self.name, "circuit", backoff);
+			},
+			Err(_) => {}
+		}
+		telemetry::request(&self.name, mode, result.as_ref().map(|_| ()), start.elapsed());
+		result
+	}
+
+	/// Uses `caps` instead of fetching them from the indexer.
+	pub fn with_caps(self, caps: Caps) -> Self {
+		*self.caps.write().unwrap() = Some(Arc::new(caps));
+		self
+	}
+
+	pub(crate) fn url(&self, credentials: &Credentials, qparams: &[(&str, SmartCow<'_>)]) -> Url {
+		endpoint::with_query(&self.base_url, credentials.query().into_iter().chain(qparams.iter().map(|(k, v)| (*k, v.as_ref()))))
+	}
+
+	/// A request for `url`, bounded by the client's [`Limits`] and carrying its cookies, and its credentials if it's
+	/// to the indexer's own origin.
+	pub(crate) fn request(&self, url: Url, credentials: &Credentials) -> transport::Request {
+		let mut request = transport::Request::get(url).max_body_size(self.parse_options.limits.max_body_size);
+		if(request.url.origin() == self.base_url.origin()) {
+			request = credentials.apply(request);
+		}
+		if let Some(cookie) = self.cookies.as_ref().and_then(|jar| jar.header(&request.url)) {
+			request = request.header("Cookie", cookie);
+		}
+		request
+	}
+
+	/// Sends a request for the URL `url` builds from the current credentials, whatever the response's status.  A
+	/// `401` is retried once with refreshed credentials if there's a [refresh hook](Self::with_credential_refresh).
+	pub(crate) async fn fetch(&self, url: impl Fn(&Credentials) -> Url) -> Result<transport::Response, Error> {
+		let credentials = self.credentials.read().unwrap().clone();
+		let response = self.send(url(&credentials), &credentials).await?;
+		let refresh = match &self.refresh {
+			Some(refresh) if(response.status == 401) => refresh,
+			_ => return Ok(response)
+		};
+		let credentials = refresh.refresh().await.map_err(Error::Credentials)?;
+		*self.credentials.write().unwrap() = credentials.clone();
+		self.send(url(&credentials), &credentials).await
+	}
+
+	async fn send(&self, url: Url, credentials: &Credentials) -> Result<transport::Response, Error> {
+		let request = self.request(url, credentials);
+		let url = request.url.clone();
+		let response = transport::send(self.transport.as_ref(), request).await?;
+		if let Some(jar) = &self.cookies {
+			jar.store(&url, &response);
+		}
+		if let Some(wait) = response.retry_after().filter(|_| !response.is_success()) {
+			telemetry::backoff(&self.name, "retry_after", wait);
+		}
+		Ok(response)
+	}
+
+	/// Fetches a raw feed, transcoded to UTF-8 as described under [`with_encoding()`](Self::with_encoding).
 	#[instrument(err, level = "debug", skip(self))]
-	pub async fn get(&self, t: TorznabCategory, qparams: Vec<(&str, SmartCow<'_>)>) -> Result<Bytes, reqwest::Error> {
-		let url = format!("{}?category={}&apikey={}&{}", self.base_url, t.as_u32(), self.apikey, qparams.into_iter()
-			.map(|(k, v)| {
-				let mut s = String::from(urlencoding::encode(k));
-				s.push('=');
-				s.push_str(&urlencoding::encode(&v));
-				s
-			})
-			.join("&")
-		);
-		println!("{}", url);
-		self.http.get(&url).send().await?.error_for_status()?.bytes().await
+	pub async fn get(&self, t: TorznabCategory, mut qparams: Vec<(&str, SmartCow<'_>)>) -> Result<Bytes, Error> {
+		qparams.insert(0, ("category", SmartCow::Owned(t.as_u32().to_string().into())));
+		let response = transport::success(self.fetch(|credentials| self.url(credentials, &qparams)).await?)?;
+		telemetry::bytes_received(&self.name, response.body.len());
+		let content_type = response.header("Content-Type").map(str::to_string);
+		Ok(charset::to_utf8(response.body, content_type.as_deref(), self.encoding))
 	}
 
+	/// Fetches the indexer's capabilities.
 	#[instrument(err, level = "debug", skip(self))]
-	pub async fn get_items(&self, t: TorznabCategory, qparams: Vec<(&str, SmartCow<'_>)>) -> Result<Vec<Item>, Error> {
-		let bytes = self.get(t, qparams).await?.reader();
-		let channel = Channel::read_from(bytes)?;
-		Ok(channel.into_items())
+	pub async fn caps(&self) -> Result<Caps, Error> {
+		self.tracked("caps", async {
+			let url = |credentials: &Credentials| self.url(credentials, &[("t", SmartCow::Borrowed("caps"))]);
+			let bytes = transport::success(self.fetch(url).await?)?.body;
+			telemetry::bytes_received(&self.name, bytes.len());
+			Caps::parse(&bytes)
+		}).await
 	}
-	//Sorry, but in new torznab there is no such parameters
+
+	/// The indexer's capabilities, fetched on first use and cached until [`invalidate_caps()`](Self::invalidate_caps).
+	pub async fn cached_caps(&self) -> Result<Arc<Caps>, Error> {
+		if let Some(caps) = self.caps.read().unwrap().as_ref() {
+			telemetry::caps_cache(&self.name, true);
+			return Ok(caps.clone());
+		}
+		telemetry::caps_cache(&self.name, false);
+		let caps = Arc::new(self.caps().await?);
+		*self.caps.write().unwrap() = Some(caps.clone());
+		Ok(caps)
+	}
+
+	pub fn invalidate_caps(&self) {
+		*self.caps.write().unwrap() = None;
+	}
+
+	/// Searches category `t` with raw query parameters, returning the whole feed; [`search()`](Self::search) is the
+	/// usual way.  The feed is parsed with the client's [`ParseOptions`], and so bounded by its [`Limits`].
+	#[instrument(err, level = "debug", skip(self))]
+	pub async fn get_items(&self, t: TorznabCategory, qparams: Vec<(&str, SmartCow<'_>)>) -> Result<Feed, Error> {
+		let feed = self.tracked("search", async {
+			let bytes = self.get(t, qparams).await?;
+			self.parse_options.parse_feed(&bytes)
+		}).await?;
+		telemetry::results(&self.name, &feed.results);
+		Ok(feed)
+	}
+
+	/// Searches category `t`.  If `query` has a [`max_age`](Query::max_age), results known to be older are dropped.
 	#[instrument(err, level = "info", skip(self))]
-	pub async fn tvsearch(&self, q: Option<&str>) -> Result<Vec<Result<Torrent, Error>>, Error> {
-		let mut qparams = Vec::new();
-		if let Some(v) = q {
-			qparams.push(("q", SmartCow::Borrowed(v)));
+	pub async fn search(&self, t: TorznabCategory, query: &Query) -> Result<Vec<Result<SearchResult, Error>>, Error> {
+		let mode = query.mode.map(SearchMode::as_str).unwrap_or("search");
+		let feed = self.tracked(mode, async {
+			let bytes = self.get(t, query.qparams()).await?;
+			self.parse_options.parse_feed(&bytes)
+		}).await?;
+		let mut results = feed.results;
+		if let Some(max_age) = query.max_age {
+			results.retain(|result| !matches!(result, Ok(result) if result.is_older_than(max_age)));
 		}
-		let items = self.get_items(TorznabCategory::Tv5000, qparams).await?;
-		Ok(items.into_iter().map(torrent::from_item).collect::<Vec<_>>())
+		telemetry::results(&self.name, &results);
+		Ok(results)
+	}
+
+	/// Like [`search()`](Self::search), but first adapts `query` to the indexer's [cached caps](Self::cached_caps), e.g.
+	/// turning an `imdbid` search into a search for the title and year where `imdbid` isn't supported.  The returned
+	/// [`Strategy`] says what was actually sent.
+	#[instrument(err, level = "info", skip(self))]
+	pub async fn negotiated_search(&self, t: TorznabCategory, query: &Query) -> Result<SearchResponse, Error> {
+		let caps = self.cached_caps().await?;
+		let (query, strategy) = negotiate::negotiate(&caps, query)?;
+		let results = self.search(t, &query).await?;
+		Ok(SearchResponse{results, strategy})
 	}
 	//Sorry, but in new torznab there is no such parameters
 	#[instrument(err, level = "info", skip(self))]
-	pub async fn moviesearch(&self, q: Option<&str>) -> Result<Vec<Result<Torrent, Error>>, Error> {
-		let mut qparams = Vec::new();
-		if let Some(v) = q {
-			qparams.push(("q", SmartCow::Borrowed(v)))
-		}
-		let items = self.get_items(TorznabCategory::Movies2000, qparams).await?;
-		Ok(items.into_iter().map(torrent::from_item).collect::<Vec<_>>())
+	pub async fn tvsearch(&self, q: Option<&str>) -> Result<Vec<Result<SearchResult, Error>>, Error> {
+		self.search(TorznabCategory::Tv5000, &Query::from(q)).await
+	}
+	//Sorry, but in new torznab there is no such parameters
+	#[instrument(err, level = "info", skip(self))]
+	pub async fn moviesearch(&self, q: Option<&str>) -> Result<Vec<Result<SearchResult, Error>>, Error> {
+		self.search(TorznabCategory::Movies2000, &Query::from(q)).await
 	}
 
 	#[instrument(err, level = "info", skip(self))]
-	pub async fn audiosearch(&self, q: Option<&str>) -> Result<Vec<Result<Torrent, Error>>, Error>{
-		let mut qparams = Vec::new();
-		if let Some(v) = q {
-			qparams.push(("q", SmartCow::Borrowed(v)));
-		}
-		let items = self.get_items(TorznabCategory::Audio3000, qparams).await?;
-		Ok(items.into_iter().map(torrent::from_item).collect::<Vec<_>>())
+	pub async fn audiosearch(&self, q: Option<&str>) -> Result<Vec<Result<SearchResult, Error>>, Error>{
+		self.search(TorznabCategory::Audio3000, &Query::from(q)).await
 	}
 }
 
 #[cfg(test)]
 mod tests {
+	use bytes::Buf;
+	use rss::Channel;
+
 	use super::*;
+
+	fn strict() -> ParseOptions {
+		ParseOptions::default()
+	}
 	#[test]
 	fn issue001_broken_jackett_feed() {
 		let s = /* {{{ */ r#"
@@ -387,8 +723,259 @@ mod tests {
 		let items = channel.into_items();
 		assert!(items.len() == 11);
 		for item in items.into_iter() {
-			torrent::from_item(item).unwrap();
+			torrent::from_item(item.into(), strict()).unwrap();
 		}
 	}
-}
 
+	const BROKEN_ITEM: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+		<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+		  <channel>
+			<item>
+			  <title>Some.Release.2021.1080p.WEB-DL-GRP</title>
+			  <guid>https://example.com/details/1</guid>
+			  <size>1234</size>
+			  <category>2040</category>
+			  <category>Movies/HD</category>
+			  <torznab:attr name="seeders" value="many" />
+			  <torznab:attr name="magneturl" value="magnet:?xt=urn:btih:cea91d952f2c94777944059284ed7422f6d3c7c8" />
+			</item>
+		  </channel>
+		</rss>
+	"#;
+
+	#[test]
+	fn swarm_counts() {
+		let item = |attrs: &str| {
+			let feed = format!(r#"<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed"><channel><item>
+				<title>t</title><link>magnet:?xt=urn:btih:00</link><enclosure url="" length="1" type="application/x-bittorrent" />
+				{}
+			</item></channel></rss>"#, attrs);
+			torrent::from_item(feed::via_rss::read_items(feed.as_bytes()).unwrap().remove(0), strict()).unwrap()
+		};
+		let result = item(r#"<torznab:attr name="seeders" value="70000" /><torznab:attr name="peers" value="10" />"#);
+		assert_eq!((result.seeders, result.leechers, result.peers), (Some(70000), Some(0), Some(10)));
+		#[cfg(not(feature = "require-parse-names"))]
+		assert_eq!(result.to_torrent().seeders, Some(u16::MAX));
+		let result = item(r#"<torznab:attr name="seeders" value="-1" /><torznab:attr name="leechers" value="3" />"#);
+		assert_eq!((result.seeders, result.leechers, result.peers), (None, Some(3), None));
+		let result = item(r#"<torznab:attr name="seeders" value="0" />"#);
+		assert_eq!((result.seeders, result.leechers, result.peers), (Some(0), None, None));
+		let result = item(r#"<torznab:attr name="minimumratio" value="1.5" /><torznab:attr name="minimumseedtime" value="172800" />"#);
+		assert_eq!(result.seed_requirements(), SeedRequirements{minimum_ratio: Some(1.5), minimum_seedtime: Some(std::time::Duration::from_secs(172800))});
+		let result = item(r#"<torznab:attr name="minimumratio" value="NaN" /><torznab:attr name="uploadvolumefactor" value="-1" />"#);
+		assert_eq!((result.minimum_ratio, result.upload_volume_factor), (None, None));
+		assert_eq!(result.warnings.iter().map(|w| (w.field, w.value.as_deref())).collect::<Vec<_>>(), [("minimumratio", Some("NaN")), ("uploadvolumefactor", Some("-1"))]);
+	}
+
+	#[test]
+	fn newznab_item() {
+		let feed = r#"<?xml version="1.0" encoding="UTF-8"?>
+			<rss version="2.0" xmlns:newznab="http://www.newznab.com/DTD/2010/feeds/attributes/">
+			  <channel>
+				<item>
+				  <title>Some.Show.S01E01.720p.HDTV.x264-GRP</title>
+				  <guid isPermaLink="true">https://nzb.example/details/abc</guid>
+				  <prowlarrindexer id="5" type="private">NZBgeek</prowlarrindexer>
+				  <link>https://nzb.example/getnzb/abc.nzb&amp;i=1&amp;r=key</link>
+				  <pubDate>Sat, 03 Apr 2021 05:29:49 +0000</pubDate>
+				  <category>5040</category>
+				  <enclosure url="https://nzb.example/getnzb/abc.nzb&amp;i=1&amp;r=key" length="1073741824" type="application/x-nzb" />
+				  <newznab:attr name="category" value="5040" />
+				  <newznab:attr name="size" value="1073741824" />
+				  <newznab:attr name="poster" value="poster@example.com (Poster)" />
+				  <newznab:attr name="group" value="alt.binaries.teevee" />
+				  <newznab:attr name="usenetdate" value="Sat, 03 Apr 2021 04:00:00 +0000" />
+				  <newznab:attr name="grabs" value="42" />
+				  <newznab:attr name="password" value="0" />
+				</item>
+			  </channel>
+			</rss>
+		"#;
+		let result = torrent::from_item(feed::via_rss::read_items(feed.as_bytes()).unwrap().remove(0), strict()).unwrap();
+		assert_eq!(result.protocol, Protocol::Usenet);
+		assert_eq!(result.grabs, Some(42));
+		assert_eq!(result.indexer, Some(IndexerSource{id: Some("5".to_string()), name: Some("NZBgeek".to_string())}));
+		let usenet = result.usenet.unwrap();
+		assert_eq!(usenet.poster.as_deref(), Some("poster@example.com (Poster)"));
+		assert_eq!(usenet.groups, vec!["alt.binaries.teevee"]);
+		assert_eq!(usenet.password, Some(PasswordStatus::None));
+		assert_eq!(usenet.posted_at.unwrap().to_rfc3339(), "2021-04-03T04:00:00+00:00");
+	}
+
+	#[test]
+	fn strict_errors_name_item_and_field() {
+		let item = feed::via_rss::read_items(BROKEN_ITEM.as_bytes()).unwrap().remove(0);
+		match torrent::from_item(item, strict()) {
+			Err(Error::Item{guid, field, ..}) => {
+				assert_eq!(guid.as_deref(), Some("https://example.com/details/1"));
+				assert_eq!(field, "size");
+			},
+			other => panic!("unexpected result {:?}", other)
+		}
+	}
+
+	#[test]
+	fn lenient_mode_recovers_and_warns() {
+		let item = feed::via_rss::read_items(BROKEN_ITEM.as_bytes()).unwrap().remove(0);
+		let result = torrent::from_item(item, ParseOptions{mode: ParseMode::Lenient, ..Default::default()}).unwrap();
+		assert_eq!(result.size, 1234);
+		assert_eq!(result.categories, vec![2040]);
+		assert!(result.link.starts_with("magnet:"));
+		assert_eq!(result.seeders, None);
+		let fields = result.warnings.iter().map(|w| (w.field, w.kind, w.recovered_from)).collect::<Vec<_>>();
+		assert_eq!(fields, vec![
+			("size", WarningKind::Missing, Some("`size` element")),
+			("category", WarningKind::Invalid, None),
+			("link", WarningKind::Missing, Some("`magneturl` attr")),
+			("seeders", WarningKind::Invalid, None)
+		]);
+		assert_eq!(result.warnings[3].value.as_deref(), Some("many"));
+	}
+
+	#[test]
+	fn optional_metadata_never_fails_items() {
+		let feed = r#"<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed"><channel><item>
+			<title>t</title><guid>1</guid><link>magnet:?xt=urn:btih:00</link><pubDate>yesterday</pubDate>
+			<enclosure url="" length="1" type="application/x-bittorrent" />
+			<torznab:attr name="grabs" value="lots" />
+			<torznab:attr name="downloadvolumefactor" value="free" />
+			<torznab:attr name="uploadvolumefactor" value="1" />
+			<torznab:attr name="minimumratio" value="?" />
+			<torznab:attr name="usenetdate" value="Sat, 03 Apr 2021 04:00:00 +0000" />
+		</item></channel></rss>"#;
+		let result = torrent::from_item(feed::via_rss::read_items(feed.as_bytes()).unwrap().remove(0), strict()).unwrap();
+		assert_eq!((result.grabs, result.download_volume_factor, result.upload_volume_factor, result.minimum_ratio), (None, None, Some(1.0), None));
+		assert_eq!(result.published_at.unwrap().to_rfc3339(), "2021-04-03T04:00:00+00:00");
+		let fields = result.warnings.iter().map(|w| (w.field, w.kind, w.recovered_from)).collect::<Vec<_>>();
+		assert_eq!(fields, vec![
+			("minimumratio", WarningKind::Invalid, None),
+			("downloadvolumefactor", WarningKind::Invalid, None),
+			("pubDate", WarningKind::Invalid, Some("`usenetdate` attr")),
+			("grabs", WarningKind::Invalid, None)
+		]);
+
+		let usenet = feed.replace("torznab", "newznab").replace("<pubDate>yesterday</pubDate>", r#"<newznab:attr name="password" value="bogus" />"#).replace("Sat, 03 Apr 2021", "someday").replace("application/x-bittorrent", "application/x-nzb");
+		let result = torrent::from_item(feed::via_rss::read_items(usenet.as_bytes()).unwrap().remove(0), strict()).unwrap();
+		let usenet_info = result.usenet.as_ref().unwrap();
+		assert_eq!((result.published_at, usenet_info.posted_at, usenet_info.password), (None, None, None));
+		assert_eq!(result.warnings.iter().filter(|w| ["usenetdate", "pubDate", "password"].contains(&w.field)).map(|w| w.field).collect::<Vec<_>>(), ["usenetdate", "password"]);
+
+		let title_missing = feed.replace("<title>t</title>", "");
+		assert!(matches!(torrent::from_item(feed::via_rss::read_items(title_missing.as_bytes()).unwrap().remove(0), strict()), Err(Error::Item{field: "title", ..})));
+	}
+
+	#[tokio::test]
+	async fn charsets() {
+		let feed = |title: &str| format!(r#"<rss version="2.0"><channel><item>
+			<title>{}</title><link>magnet:?xt=urn:btih:00</link><enclosure url="" length="1" type="application/x-bittorrent" />
+		</item></channel></rss>"#, title);
+		let cp1251 = encoding_rs::WINDOWS_1251.encode(&feed("Брат.1997.1080p")).0.into_owned();
+		let mojibake = feed("Ð‘Ñ€Ð°Ñ‚.1997.1080p");
+		let client = Client::with_transport("http://indexer.invalid/api", "", transport::from_fn(move |request| {
+			Ok(match request.url.query_pairs().find(|(k, _)| k == "q").map(|(_, q)| q.into_owned()).as_deref() {
+				Some("header") => transport::Response::new(200, cp1251.clone()).with_header("Content-Type", "application/rss+xml; charset=windows-1251"),
+				Some("undeclared") => transport::Response::new(200, cp1251.clone()),
+				_ => transport::Response::new(200, mojibake.clone())
+			})
+		})).unwrap();
+		let name = |results: Vec<Result<SearchResult, Error>>| results.into_iter().next().unwrap().unwrap().name;
+		assert_eq!(name(client.search(TorznabCategory::Movies2000, &Query::new().q("header")).await.unwrap()), "Брат.1997.1080p");
+		assert_eq!(client.get_items(TorznabCategory::Movies2000, Query::new().q("header").qparams()).await.unwrap().results[0].as_ref().unwrap().name, "Брат.1997.1080p");
+		assert_eq!(name(client.search(TorznabCategory::Movies2000, &Query::new().q("mojibake")).await.unwrap()), "Ð‘Ñ€Ð°Ñ‚.1997.1080p");
+		let client = client.with_title_repair(true);
+		let result = client.search(TorznabCategory::Movies2000, &Query::new().q("mojibake")).await.unwrap().remove(0).unwrap();
+		assert_eq!(result.name, "Брат.1997.1080p");
+		assert_eq!(result.warnings.iter().map(|w| (w.field, w.kind, w.value.as_deref())).collect::<Vec<_>>(), [("title", WarningKind::Repaired, Some("Ð‘Ñ€Ð°Ñ‚.1997.1080p"))]);
+		let client = client.with_encoding(encoding_rs::WINDOWS_1251);
+		assert_eq!(name(client.search(TorznabCategory::Movies2000, &Query::new().q("undeclared")).await.unwrap()), "Брат.1997.1080p");
+
+		let declared = [&b"<?xml version=\"1.0\" encoding=\"windows-1251\"?>"[..], &encoding_rs::WINDOWS_1251.encode(&feed("Брат")).0].concat();
+		assert_eq!(parse_feed(&declared).unwrap().results[0].as_ref().unwrap().name, "Брат");
+	}
+
+	#[tokio::test]
+	async fn get_items_limits() {
+		let client = Client::with_transport("http://indexer.invalid/api", "", transport::from_fn(|_| Ok(transport::Response::new(200, r#"<rss version="2.0"><channel>
+			<item><title>a</title><link>magnet:?xt=urn:btih:00</link><size>1</size></item>
+			<item><title>b</title><link>magnet:?xt=urn:btih:01</link><size>1</size></item>
+		</channel></rss>"#)))).unwrap();
+		assert_eq!(client.get_items(TorznabCategory::Movies2000, vec![]).await.unwrap().results.len(), 2);
+		let client = client.with_limits(Limits{max_items: 1, ..Limits::default()});
+		assert!(matches!(client.get_items(TorznabCategory::Movies2000, vec![]).await, Err(Error::TooManyItems{limit: 1})));
+	}
+
+	#[cfg(feature = "metrics")]
+	#[test]
+	fn get_items_records_results() {
+		use metrics_util::debugging::DebugValue;
+		use metrics_util::debugging::DebuggingRecorder;
+
+		let client = Client::with_transport("http://indexer.invalid/api", "", transport::from_fn(|_| Ok(transport::Response::new(200, r#"<rss version="2.0"><channel>
+			<item><title>a</title><link>magnet:?xt=urn:btih:00</link><enclosure url="" length="1" type="application/x-bittorrent" /></item>
+			<item><link>magnet:?xt=urn:btih:01</link><enclosure url="" length="1" type="application/x-bittorrent" /></item>
+		</channel></rss>"#)))).unwrap().with_name("rarbg");
+		let recorder = DebuggingRecorder::new();
+		let snapshotter = recorder.snapshotter();
+		metrics::with_local_recorder(&recorder, || {
+			let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+			runtime.block_on(client.get_items(TorznabCategory::Movies2000, vec![])).unwrap();
+		});
+		let snapshot = snapshotter.snapshot().into_vec();
+		let counter = |name: &str| snapshot.iter().find(|(key, _, _, _)| key.key().name() == name).map(|(_, _, _, value)| value);
+		assert_eq!(counter("torznab_items_total"), Some(&DebugValue::Counter(1)));
+		assert_eq!(counter("torznab_parse_failures_total"), Some(&DebugValue::Counter(1)));
+	}
+
+	#[tokio::test]
+	async fn health_counts_unavailability() {
+		let client = Client::with_transport("http://indexer.invalid/api", "", transport::from_fn(|request| {
+			let status = request.url.query_pairs().find(|(k, _)| k == "status").map(|(_, v)| v.parse().unwrap()).unwrap();
+			Ok(transport::Response::new(status, "<rss />"))
+		})).unwrap().with_health_policy(HealthPolicy{failure_threshold: 1, ..HealthPolicy::default()});
+		for _ in 0..2 {
+			assert!(matches!(client.get_items(TorznabCategory::Movies2000, vec![("status", "404".into())]).await, Err(Error::Status(404))));
+		}
+		assert_eq!(client.health().failures, 0);
+		assert!(matches!(client.get_items(TorznabCategory::Movies2000, vec![("status", "503".into())]).await, Err(Error::Status(503))));
+		assert!(!client.health().is_available());
+	}
+
+	#[cfg(feature = "reqwest")]
+	#[tokio::test]
+	async fn negotiated_search() {
+		use std::sync::atomic::{AtomicUsize, Ordering};
+		use test_server::Response;
+
+		let caps_requests = Arc::new(AtomicUsize::new(0));
+		let counter = caps_requests.clone();
+		let base_url = test_server::serve(move |request| {
+			assert_eq!(request.query("apikey").as_deref(), Some("key"));
+			match request.query("t").as_deref() {
+				Some("caps") => {
+					counter.fetch_add(1, Ordering::SeqCst);
+					Response::new(200, r#"<caps><searching><search available="yes" /><movie-search available="yes" supportedParams="q" /></searching></caps>"#)
+				},
+				Some("movie") => {
+					assert_eq!(request.query("imdbid"), None);
+					assert_eq!(request.query("q").as_deref(), Some("Heat 1995"));
+					Response::new(200, r#"<rss version="2.0"><channel><title>t</title></channel></rss>"#)
+				},
+				_ => Response::new(400, "")
+			}
+		}).await;
+
+		let client = Client::new(format!("{}/api", base_url), "key").unwrap();
+		let query = Query::new().mode(SearchMode::MovieSearch).imdbid("tt0113277").title("Heat").year(1995);
+		for _ in 0..2 {
+			let response = tokio::spawn({
+				let client = client.clone();
+				let query = query.clone();
+				async move { client.negotiated_search(TorznabCategory::Movies2000, &query).await }
+			}).await.unwrap().unwrap();
+			assert!(response.results.is_empty());
+			assert_eq!(response.strategy.dropped, ["imdbid", "year"]);
+			assert_eq!(response.strategy.fallback_q.as_deref(), Some("Heat 1995"));
+		}
+		assert_eq!(caps_requests.load(Ordering::SeqCst), 1);
+	}
+}
@@ -0,0 +1,141 @@
+use crate::Caps;
+use crate::Error;
+use crate::Query;
+use crate::SearchMode;
+use crate::SearchResult;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// How a [`Query`] was adapted to what the indexer supports.
+pub struct Strategy {
+	/// The search function that was asked for.
+	pub requested: SearchMode,
+	/// The search function actually used; [`SearchMode::Search`] if the requested one isn't available.
+	pub mode: SearchMode,
+	/// Requested parameters the indexer doesn't support, which were left out.
+	pub dropped: Vec<&'static str>,
+	/// Text the dropped parameters were folded into, sent as `q` in their place.
+	pub fallback_q: Option<String>
+}
+
+impl Strategy {
+	/// Whether the query was sent as asked.
+	pub fn is_exact(&self) -> bool {
+		self.mode == self.requested && self.dropped.is_empty()
+	}
+}
+
+#[derive(Debug)]
+/// Results of a search along with the [`Strategy`] used to get them.
+pub struct SearchResponse {
+	pub results: Vec<Result<SearchResult, Error>>,
+	pub strategy: Strategy
+}
+
+const IDS: [&str; 4] = ["imdbid", "tmdbid", "tvdbid", "tvmazeid"];
+
+/// Rewrites `query` into one the indexer described by `caps` can answer.  IDs it can't search by are dropped, and
+/// replaced with a text search for the query's title (and year, season and episode) if there's no `q` already.
+pub(crate) fn negotiate(caps: &Caps, query: &Query) -> Result<(Query, Strategy), Error> {
+	let requested = query.mode.unwrap_or(SearchMode::Search);
+	let mode = if(caps.is_available(requested)) {
+		requested
+	} else if(caps.is_available(SearchMode::Search)) {
+		SearchMode::Search
+	} else {
+		return Err(Error::UnsupportedMode(requested));
+	};
+
+	let mut query = Query{mode: Some(mode), ..query.clone()};
+	let mut dropped = Vec::new();
+	let mut drop_if_unsupported = |param: &'static str, present: bool| -> bool {
+		let drop = present && !caps.supports(mode, param);
+		if(drop) {
+			dropped.push(param);
+		}
+		drop
+	};
+	if(drop_if_unsupported("imdbid", query.imdbid.is_some())) { query.imdbid = None; }
+	if(drop_if_unsupported("tmdbid", query.tmdbid.is_some())) { query.tmdbid = None; }
+	if(drop_if_unsupported("tvdbid", query.tvdbid.is_some())) { query.tvdbid = None; }
+	if(drop_if_unsupported("tvmazeid", query.tvmazeid.is_some())) { query.tvmazeid = None; }
+	let season = drop_if_unsupported("season", query.season.is_some()).then(|| query.season.take()).flatten();
+	let episode = drop_if_unsupported("ep", query.episode.is_some()).then(|| query.episode.take()).flatten();
+	let year = drop_if_unsupported("year", query.year.is_some()).then(|| query.year.take()).flatten();
+
+	let dropped_ids = dropped.iter().any(|param| IDS.contains(param));
+	let ids_left = query.imdbid.is_some() || query.tmdbid.is_some() || query.tvdbid.is_some() || query.tvmazeid.is_some();
+	let mut fallback_q = None;
+	let base = match (&query.q, &query.title) {
+		(Some(q), _) => Some(q.clone()),
+		(None, Some(title)) if(dropped_ids && !ids_left) => Some(title.clone()),
+		(None, _) if(dropped_ids && !ids_left) => return Err(Error::UnsupportedParam(dropped[0])),
+		(None, _) => None
+	};
+	if let Some(mut q) = base {
+		let folded = season.is_some() || episode.is_some() || year.is_some();
+		if(folded || query.q.is_none()) {
+			if let Some(year) = year {
+				q.push_str(&format!(" {}", year));
+			}
+			match (season, episode) {
+				(Some(season), Some(episode)) => q.push_str(&format!(" S{:02}E{:02}", season, episode)),
+				(Some(season), None) => q.push_str(&format!(" S{:02}", season)),
+				(None, Some(episode)) => q.push_str(&format!(" E{:02}", episode)),
+				(None, None) => {}
+			}
+			query.q = Some(q.clone());
+			fallback_q = Some(q);
+		}
+	}
+
+	Ok((query, Strategy{requested, mode, dropped, fallback_q}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn caps() -> Caps {
+		Caps::parse(br#"<caps>
+			<searching>
+				<search available="yes" supportedParams="q" />
+				<tv-search available="no" supportedParams="q,season,ep,tvdbid" />
+				<movie-search available="yes" supportedParams="q,tmdbid" />
+			</searching>
+		</caps>"#).unwrap()
+	}
+
+	#[test]
+	fn imdbid_falls_back_to_title_and_year() {
+		let query = Query::new().mode(SearchMode::MovieSearch).imdbid("tt0111161").title("The Shawshank Redemption").year(1994);
+		let (query, strategy) = negotiate(&caps(), &query).unwrap();
+		assert_eq!(query.q.as_deref(), Some("The Shawshank Redemption 1994"));
+		assert_eq!(query.imdbid, None);
+		assert_eq!(strategy.mode, SearchMode::MovieSearch);
+		assert_eq!(strategy.dropped, ["imdbid", "year"]);
+		assert!(!strategy.is_exact());
+
+		let query = Query::new().mode(SearchMode::MovieSearch).imdbid("tt0111161").tmdbid(278).title("The Shawshank Redemption");
+		let (query, strategy) = negotiate(&caps(), &query).unwrap();
+		assert_eq!((query.q, query.tmdbid), (None, Some(278)));
+		assert_eq!(strategy.fallback_q, None);
+
+		let query = Query::new().mode(SearchMode::MovieSearch).imdbid("tt0111161");
+		assert!(matches!(negotiate(&caps(), &query), Err(Error::UnsupportedParam("imdbid"))));
+	}
+
+	#[test]
+	fn unavailable_mode_falls_back_to_search() {
+		let query = Query::new().mode(SearchMode::TvSearch).tvdbid(81189).title("Breaking Bad").season(1).episode(2);
+		let (query, strategy) = negotiate(&caps(), &query).unwrap();
+		assert_eq!(query.mode, Some(SearchMode::Search));
+		assert_eq!(query.q.as_deref(), Some("Breaking Bad S01E02"));
+		assert_eq!((query.season, query.episode), (None, None));
+		assert_eq!(strategy.requested, SearchMode::TvSearch);
+
+		let query = Query::new().mode(SearchMode::MovieSearch).q("Heat").tmdbid(949);
+		let (_, strategy) = negotiate(&caps(), &query).unwrap();
+		assert!(strategy.is_exact());
+		assert!(matches!(negotiate(&Caps::default(), &query), Err(Error::UnsupportedMode(SearchMode::MovieSearch))));
+	}
+}
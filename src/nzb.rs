@@ -0,0 +1,214 @@
+use std::collections::BTreeSet;
+use std::io::BufRead;
+
+use chrono::DateTime;
+use chrono::TimeZone;
+use chrono::Utc;
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::xml::attr;
+use crate::xml::element_text;
+use crate::xml::skip;
+use crate::Error;
+
+/// How far the size of an NZB may stray from what the indexer advertised before [`Nzb::validate_size()`] rejects it.
+/// Segment sizes count yEnc-encoded article bytes, which run a few percent over the decoded size most indexers report.
+pub const SIZE_TOLERANCE: f64 = 0.1;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A single article making up part of an [`NzbFile`].
+pub struct Segment {
+	pub bytes: u64,
+	pub number: u32,
+	pub message_id: String
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A file posted to Usenet, split across one or more [`Segment`]s.
+pub struct NzbFile {
+	pub poster: String,
+	/// When the file was posted; `None` if the date is missing or isn't a Unix timestamp.
+	pub date: Option<DateTime<Utc>>,
+	pub subject: String,
+	pub groups: Vec<String>,
+	pub segments: Vec<Segment>
+}
+
+impl NzbFile {
+	pub fn bytes(&self) -> u64 {
+		self.segments.iter().map(|segment| segment.bytes).sum()
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A parsed NZB document.
+pub struct Nzb {
+	/// `<meta>` elements from the `<head>`, as `(type, value)` pairs in document order.
+	pub meta: Vec<(String, String)>,
+	pub files: Vec<NzbFile>
+}
+
+impl Nzb {
+	pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+		let mut reader = Reader::from_reader(bytes);
+		reader.config_mut().expand_empty_elements = true;
+		let mut nzb = Self::default();
+		let mut buf = Vec::new();
+		let mut seen_root = false;
+		loop {
+			match reader.read_event_into(&mut buf)? {
+				Event::Start(element) => match element.local_name().as_ref() {
+					b"nzb" => seen_root = true,
+					b"head" => {},
+					b"meta" => {
+						let kind = attr(&reader, &element, b"type")?.unwrap_or_default();
+						let value = element_text(&mut reader)?.unwrap_or_default();
+						nzb.meta.push((kind, value));
+					},
+					b"file" => {
+						let file = read_file(&mut reader, &element)?;
+						nzb.files.push(file);
+					},
+					_ => skip(&mut reader, &element)?
+				},
+				Event::Eof => break,
+				_ => {}
+			}
+			buf.clear();
+		}
+		if(!seen_root) {
+			return Err(Error::MissingField("nzb".to_string()));
+		}
+		Ok(nzb)
+	}
+
+	/// First `<meta>` value of type `kind`.
+	pub fn meta(&self, kind: &str) -> Option<&str> {
+		self.meta.iter().find(|(k, _)| k == kind).map(|(_, v)| v.as_str())
+	}
+
+	pub fn title(&self) -> Option<&str> {
+		self.meta("title")
+	}
+
+	/// Password needed to extract the release, if the NZB carries one.
+	pub fn password(&self) -> Option<&str> {
+		self.meta("password")
+	}
+
+	/// Sum of all segment sizes.
+	pub fn total_bytes(&self) -> u64 {
+		self.files.iter().map(NzbFile::bytes).sum()
+	}
+
+	pub fn total_segments(&self) -> usize {
+		self.files.iter().map(|file| file.segments.len()).sum()
+	}
+
+	pub fn groups(&self) -> BTreeSet<&str> {
+		self.files.iter().flat_map(|file| file.groups.iter().map(String::as_str)).collect()
+	}
+
+	pub fn posters(&self) -> BTreeSet<&str> {
+		self.files.iter().map(|file| file.poster.as_str()).collect()
+	}
+
+	/// Checks that the NZB's total size is within `tolerance` (a fraction, e.g. [`SIZE_TOLERANCE`]) of `advertised`.
+	pub fn validate_size(&self, advertised: u64, tolerance: f64) -> Result<(), Error> {
+		let actual = self.total_bytes();
+		let difference = (actual as f64 - advertised as f64).abs();
+		if(difference > advertised as f64 * tolerance) {
+			return Err(Error::NzbSizeMismatch{advertised, actual});
+		}
+		Ok(())
+	}
+}
+
+fn read_file<R: BufRead>(reader: &mut Reader<R>, element: &BytesStart<'_>) -> Result<NzbFile, Error> {
+	let date = attr(reader, element, b"date")?
+		.and_then(|date| date.trim().parse().ok())
+		.and_then(|date| Utc.timestamp_opt(date, 0).single());
+	let mut file = NzbFile{
+		poster: attr(reader, element, b"poster")?.unwrap_or_default(),
+		date,
+		subject: attr(reader, element, b"subject")?.unwrap_or_default(),
+		..NzbFile::default()
+	};
+	let mut buf = Vec::new();
+	loop {
+		match reader.read_event_into(&mut buf)? {
+			Event::Start(element) => match element.local_name().as_ref() {
+				b"groups" | b"segments" => {},
+				b"group" => file.groups.extend(element_text(reader)?),
+				b"segment" => {
+					let bytes = attr(reader, &element, b"bytes")?.ok_or_else(|| Error::MissingField("bytes".to_string()))?.trim().parse()?;
+					let number = attr(reader, &element, b"number")?.ok_or_else(|| Error::MissingField("number".to_string()))?.trim().parse()?;
+					let message_id = element_text(reader)?.ok_or_else(|| Error::MissingField("segment".to_string()))?;
+					file.segments.push(Segment{bytes, number, message_id});
+				},
+				_ => skip(reader, &element)?
+			},
+			Event::End(element) if element.local_name().as_ref() == b"file" => break,
+			Event::Eof => return Err(Error::MissingField("</file>".to_string())),
+			_ => {}
+		}
+		buf.clear();
+	}
+	file.segments.sort_by_key(|segment| segment.number);
+	Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const NZB: &str = r#"<?xml version="1.0" encoding="iso-8859-1" ?>
+		<!DOCTYPE nzb PUBLIC "-//newzBin//DTD NZB 1.1//EN" "http://www.newzbin.com/DTD/nzb/nzb-1.1.dtd">
+		<nzb xmlns="http://www.newzbin.com/DTD/2003/nzb">
+		  <head>
+			<meta type="title">Some.Show.S01E01</meta>
+			<meta type="password">hunter2</meta>
+		  </head>
+		  <file poster="Joe Bloggs &lt;bloggs@nowhere.example&gt;" date="1071674882" subject="Here's your file!  abc-mr2a.r01 (1/2)">
+			<groups>
+			  <group>alt.binaries.newzbin</group>
+			  <group>alt.binaries.mojo</group>
+			</groups>
+			<segments>
+			  <segment bytes="102394" number="2">123456789abcdef@news.newzbin.com</segment>
+			  <segment bytes="4501" number="1">987654321fedbca@news.newzbin.com</segment>
+			</segments>
+		  </file>
+		</nzb>
+	"#;
+
+	#[test]
+	fn parse() {
+		let nzb = Nzb::parse(NZB.as_bytes()).unwrap();
+		assert_eq!(nzb.title(), Some("Some.Show.S01E01"));
+		assert_eq!(nzb.password(), Some("hunter2"));
+		assert_eq!(nzb.total_bytes(), 106895);
+		assert_eq!(nzb.total_segments(), 2);
+		assert_eq!(nzb.groups().into_iter().collect::<Vec<_>>(), ["alt.binaries.mojo", "alt.binaries.newzbin"]);
+		let file = &nzb.files[0];
+		assert_eq!(file.poster, "Joe Bloggs <bloggs@nowhere.example>");
+		assert_eq!(file.segments[0].message_id, "987654321fedbca@news.newzbin.com");
+		assert_eq!(file.date.unwrap().timestamp(), 1071674882);
+		nzb.validate_size(100000, SIZE_TOLERANCE).unwrap();
+		assert!(matches!(nzb.validate_size(200000, SIZE_TOLERANCE), Err(Error::NzbSizeMismatch{actual: 106895, ..})));
+	}
+
+	#[test]
+	fn bad_date() {
+		let nzb = Nzb::parse(NZB.replace("1071674882", "yesterday").as_bytes()).unwrap();
+		assert_eq!(nzb.files[0].date, None);
+		assert_eq!(nzb.total_bytes(), 106895);
+	}
+
+	#[test]
+	fn not_an_nzb() {
+		assert!(Nzb::parse(b"<html><body>Rate limited</body></html>").is_err());
+	}
+}
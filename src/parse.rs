@@ -0,0 +1,142 @@
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+
+use crate::Error;
+use crate::FeedReader;
+use crate::ParseMode;
+use crate::Protocol;
+use crate::SearchResult;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Channel-level metadata of a feed.
+pub struct ChannelInfo {
+	pub title: String,
+	pub link: String,
+	pub description: String,
+	pub language: Option<String>,
+	/// Paging information from `newznab:response` or `torznab:response`:  the index of the first result in this feed,
+	/// and the number of results across all pages.
+	pub offset: Option<u64>,
+	pub total: Option<u64>
+}
+
+#[derive(Debug)]
+/// A parsed Torznab or Newznab feed.
+pub struct Feed {
+	pub channel: ChannelInfo,
+	/// One entry per item, in document order; items that couldn't be parsed are errors (see [`ParseMode`]).
+	pub results: Vec<Result<SearchResult, Error>>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Bounds on what's accepted from an indexer, so that a misbehaving or hostile one can't exhaust memory.  Exceeding
+/// one fails the whole response with a dedicated [`Error`] variant.
+pub struct Limits {
+	/// Bytes in a response body, compressed or not; exceeding it is [`Error::BodyTooLarge`].
+	pub max_body_size: usize,
+	/// Items in a feed; exceeding it is [`Error::TooManyItems`].
+	pub max_items: usize,
+	/// `torznab:attr` and `newznab:attr` elements in an item; exceeding it is [`Error::TooManyAttrs`].
+	pub max_attrs: usize,
+	/// Bytes in an element's text or an attribute's value; exceeding it is [`Error::StringTooLong`].
+	pub max_string_length: usize
+}
+
+impl Default for Limits {
+	/// 16 MiB bodies of up to 10,000 items, with up to 256 attrs each and strings of up to 64 KiB.
+	fn default() -> Self {
+		Self{
+			max_body_size: 16 << 20,
+			max_items: 10_000,
+			max_attrs: 256,
+			max_string_length: 64 << 10
+		}
+	}
+}
+
+impl Limits {
+	/// No limits at all, for trusted sources.
+	pub fn unlimited() -> Self {
+		Self{
+			max_body_size: usize::MAX,
+			max_items: usize::MAX,
+			max_attrs: usize::MAX,
+			max_string_length: usize::MAX
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// How feeds are parsed.
+pub struct ParseOptions {
+	pub mode: ParseMode,
+	/// Protocol to assume for items that give no indication of their own.
+	pub protocol: Protocol,
+	pub limits: Limits,
+	/// Undo titles having been encoded as UTF-8 twice, which some aggregators do, recording a
+	/// [`WarningKind::Repaired`](crate::WarningKind::Repaired) warning for each one changed.
+	pub repair_titles: bool
+}
+
+impl ParseOptions {
+	pub fn parse_feed(self, bytes: &[u8]) -> Result<Feed, Error> {
+		self.stream(bytes)?.into_feed()
+	}
+
+	pub fn parse_feed_reader(self, reader: impl Read) -> Result<Feed, Error> {
+		self.stream(BufReader::new(reader))?.into_feed()
+	}
+
+	/// Starts reading a feed from `reader`, to be read item by item through the returned [`FeedReader`].  Fails
+	/// straight away if the document is an indexer's error response, or isn't a feed.
+	pub fn stream<R: BufRead>(self, reader: R) -> Result<FeedReader<R>, Error> {
+		FeedReader::new(reader, self)
+	}
+}
+
+/// Parses a feed, as fetched from an indexer, with the default [`ParseOptions`].  This needs no HTTP client, so works
+/// without the crate's default features.
+pub fn parse_feed(bytes: &[u8]) -> Result<Feed, Error> {
+	ParseOptions::default().parse_feed(bytes)
+}
+
+/// Like [`parse_feed()`], but reads the feed from `reader`.
+pub fn parse_feed_reader(reader: impl Read) -> Result<Feed, Error> {
+	ParseOptions::default().parse_feed_reader(reader)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn channel_and_results() {
+		let feed = parse_feed_reader(&br#"<?xml version="1.0" encoding="UTF-8"?>
+			<rss version="2.0" xmlns:newznab="http://www.newznab.com/DTD/2010/feeds/attributes/">
+			  <channel>
+				<title>NZBgeek</title>
+				<link>https://nzbgeek.info/</link>
+				<description>NZBgeek Feed</description>
+				<language>en-gb</language>
+				<newznab:response offset="100" total="1234" />
+				<item>
+				  <title>Some.Show.S01E01</title>
+				  <guid>1</guid>
+				  <link>https://nzbgeek.info/get/1</link>
+				  <enclosure url="https://nzbgeek.info/get/1" length="1000" type="application/x-nzb" />
+				</item>
+			  </channel>
+			</rss>
+		"#[..]).unwrap();
+		assert_eq!(feed.channel.title, "NZBgeek");
+		assert_eq!(feed.channel.language.as_deref(), Some("en-gb"));
+		assert_eq!((feed.channel.offset, feed.channel.total), (Some(100), Some(1234)));
+		let result = feed.results[0].as_ref().unwrap();
+		assert_eq!(result.protocol, Protocol::Usenet);
+		assert_eq!(result.size, 1000);
+
+		let err = parse_feed(br#"<?xml version="1.0" encoding="UTF-8"?><error code="100" description="Incorrect user credentials" />"#).unwrap_err();
+		assert!(matches!(err, Error::Indexer{code: Some(100), ..}));
+	}
+}
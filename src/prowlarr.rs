@@ -0,0 +1,298 @@
+use std::sync::Arc;
+
+use chrono::DateTime;
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tracing::instrument;
+use url::Url;
+
+use crate::auth::Refresh;
+use crate::endpoint;
+use crate::Client;
+use crate::CookieJar;
+use crate::Credentials;
+use crate::Error;
+use crate::Limits;
+use crate::Privacy;
+use crate::Protocol;
+use crate::transport;
+use crate::Transport;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawIndexer {
+	id: u32,
+	name: Option<String>,
+	protocol: Option<String>,
+	privacy: Option<String>,
+	#[serde(default)]
+	priority: i32,
+	#[serde(default)]
+	enable: bool,
+	#[serde(default)]
+	tags: Vec<u32>,
+	implementation: Option<String>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawIndexerStatus {
+	indexer_id: u32,
+	disabled_till: Option<DateTime<Utc>>,
+	initial_failure: Option<DateTime<Utc>>,
+	most_recent_failure: Option<DateTime<Utc>>
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An indexer as listed by Prowlarr's `/api/v1/indexer`.
+pub struct ProwlarrIndexer {
+	pub id: u32,
+	pub name: Option<String>,
+	/// Indexers whose protocol Prowlarr reports as unknown are assumed to be torrent indexers.
+	pub protocol: Protocol,
+	pub privacy: Option<Privacy>,
+	/// Lower values are preferred; Prowlarr defaults to 25.
+	pub priority: i32,
+	pub enabled: bool,
+	/// IDs of the indexer's tags; see [`Prowlarr::tags()`] for their labels.
+	pub tags: Vec<u32>,
+	pub implementation: Option<String>
+}
+
+impl From<RawIndexer> for ProwlarrIndexer {
+	fn from(raw: RawIndexer) -> Self {
+		Self{
+			id: raw.id,
+			name: raw.name,
+			protocol: raw.protocol.and_then(|protocol| protocol.parse().ok()).unwrap_or_default(),
+			privacy: raw.privacy.and_then(|privacy| privacy.parse().ok()),
+			priority: raw.priority,
+			enabled: raw.enable,
+			tags: raw.tags,
+			implementation: raw.implementation
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An indexer's failure state, from Prowlarr's `/api/v1/indexerstatus`.  Prowlarr only lists indexers that have failed.
+pub struct ProwlarrIndexerStatus {
+	pub indexer_id: u32,
+	/// Prowlarr won't query the indexer before this time.
+	pub disabled_till: Option<DateTime<Utc>>,
+	pub initial_failure: Option<DateTime<Utc>>,
+	pub most_recent_failure: Option<DateTime<Utc>>
+}
+
+impl From<RawIndexerStatus> for ProwlarrIndexerStatus {
+	fn from(raw: RawIndexerStatus) -> Self {
+		Self{
+			indexer_id: raw.indexer_id,
+			disabled_till: raw.disabled_till,
+			initial_failure: raw.initial_failure,
+			most_recent_failure: raw.most_recent_failure
+		}
+	}
+}
+
+impl ProwlarrIndexerStatus {
+	pub fn is_disabled_at(&self, now: DateTime<Utc>) -> bool {
+		self.disabled_till.map(|till| till > now).unwrap_or(false)
+	}
+
+	pub fn is_disabled(&self) -> bool {
+		self.is_disabled_at(Utc::now())
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct Tag {
+	pub id: u32,
+	pub label: String
+}
+
+#[derive(Clone)]
+/// A Prowlarr instance, for discovering its indexers and building a [`Client`] for each.
+pub struct Prowlarr {
+	base_url: Url,
+	/// A client for the instance itself, whose transport, credentials and cookies are shared with every indexer's.
+	root: Client
+}
+
+impl Prowlarr {
+	/// `base_url` is the root of the Prowlarr instance, e.g. `http://localhost:9696`, or with the `unix-socket` feature,
+	/// `unix:/run/prowlarr/prowlarr.sock`.  `apikey` is sent in the `X-Api-Key` header, which Prowlarr accepts on its
+	/// indexers' endpoints too.
+	#[instrument(err, level = "info", skip(base_url, apikey), fields(base_url = %endpoint::redacted(&base_url.to_string())))]
+	#[cfg(feature = "reqwest")]
+	pub fn new(base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		let (transport, base_url) = crate::transport_for(&base_url.to_string())?;
+		Self::from_parts(transport, base_url, apikey)
+	}
+
+	/// Like [`new()`](Self::new), but sends requests through `transport`.
+	pub fn with_transport(base_url: impl ToString, apikey: impl ToString, transport: impl Transport + 'static) -> Result<Self, Error> {
+		Self::from_parts(Arc::new(transport), base_url, apikey)
+	}
+
+	fn from_parts(transport: Arc<dyn Transport>, base_url: impl ToString, apikey: impl ToString) -> Result<Self, Error> {
+		let base_url = endpoint::parse_base(&base_url.to_string())?;
+		let root = Client::from_parts(transport, base_url.clone(), "").with_credentials(Credentials::new(apikey).apikey_header("X-Api-Key"));
+		Ok(Self{base_url, root})
+	}
+
+	/// Replaces the instance's credentials, including the API key it was created with; see [`Credentials`].  They're
+	/// used for discovery and passed on to every indexer's client.
+	pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+		self.root = self.root.with_credentials(credentials);
+		self
+	}
+
+	/// Sets the bounds on what's accepted from the instance, for discovery and every indexer's client; see [`Limits`].
+	pub fn with_limits(mut self, limits: Limits) -> Self {
+		self.root = self.root.with_limits(limits);
+		self
+	}
+
+	/// Keeps cookies in `jar`, for discovery and every indexer's client.
+	pub fn with_cookie_jar(mut self, jar: CookieJar) -> Self {
+		self.root = self.root.with_cookie_jar(jar);
+		self
+	}
+
+	/// Calls `refresh` for new credentials when the instance answers `401 Unauthorized`; see [`Refresh`].
+	pub fn with_credential_refresh(mut self, refresh: impl Refresh + 'static) -> Self {
+		self.root = self.root.with_credential_refresh(refresh);
+		self
+	}
+
+	fn endpoint(&self, id: u32) -> Url {
+		endpoint::join(&self.base_url, &[&id.to_string(), "api"])
+	}
+
+	async fn api<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+		let url = endpoint::join(&self.base_url, &["api", "v1", path]);
+		let bytes = transport::success(self.root.fetch(|credentials| endpoint::with_query(&url, credentials.query())).await?)?.body;
+		Ok(serde_json::from_slice(&bytes)?)
+	}
+
+	/// A [`Client`] for `indexer`, speaking Newznab or Torznab according to its protocol.
+	pub fn client(&self, indexer: &ProwlarrIndexer) -> Client {
+		self.root.for_endpoint(self.endpoint(indexer.id))
+			.with_protocol(indexer.protocol)
+			.with_name(indexer.name.clone().unwrap_or_else(|| indexer.id.to_string()))
+	}
+
+	#[instrument(err, level = "info", skip(self))]
+	pub async fn indexers(&self) -> Result<Vec<ProwlarrIndexer>, Error> {
+		let indexers: Vec<RawIndexer> = self.api("indexer").await?;
+		Ok(indexers.into_iter().map(ProwlarrIndexer::from).collect())
+	}
+
+	#[instrument(err, level = "info", skip(self))]
+	pub async fn statuses(&self) -> Result<Vec<ProwlarrIndexerStatus>, Error> {
+		let statuses: Vec<RawIndexerStatus> = self.api("indexerstatus").await?;
+		Ok(statuses.into_iter().map(ProwlarrIndexerStatus::from).collect())
+	}
+
+	#[instrument(err, level = "info", skip(self))]
+	pub async fn tags(&self) -> Result<Vec<Tag>, Error> {
+		self.api("tag").await
+	}
+
+	/// Lists enabled indexers that Prowlarr hasn't disabled for failing, each with a ready [`Client`], best priority first.
+	pub async fn clients(&self) -> Result<Vec<(ProwlarrIndexer, Client)>, Error> {
+		let indexers = self.indexers().await?;
+		let statuses = self.statuses().await?;
+		let now = Utc::now();
+		let mut indexers: Vec<_> = indexers.into_iter()
+			.filter(|indexer| indexer.enabled)
+			.filter(|indexer| !statuses.iter().any(|status| status.indexer_id == indexer.id && status.is_disabled_at(now)))
+			.collect();
+		indexers.sort_by_key(|indexer| indexer.priority);
+		Ok(indexers.into_iter().map(|indexer| {
+			let client = self.client(&indexer);
+			(indexer, client)
+		}).collect())
+	}
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+	use super::*;
+	use crate::test_server::serve;
+	use crate::test_server::Response;
+
+	#[tokio::test]
+	async fn stand_in() {
+		let base_url = serve(|request| {
+			if(request.header("X-Api-Key") != Some("key")) {
+				return Response::new(401, "");
+			}
+			let body = match request.path() {
+				"/api/v1/indexer" => r#"[
+					{"id": 1, "name": "1337x", "protocol": "torrent", "privacy": "public", "priority": 25, "enable": true, "tags": [], "implementation": "Cardigann"},
+					{"id": 2, "name": "NZBgeek", "protocol": "usenet", "privacy": "private", "priority": 10, "enable": true, "tags": [1]},
+					{"id": 3, "name": "Beyond-HD", "protocol": "torrent", "privacy": "semiPrivate", "priority": 1, "enable": true, "tags": [1]},
+					{"id": 4, "name": "Disabled", "protocol": "torrent", "privacy": "private", "priority": 1, "enable": false, "tags": []}
+				]"#,
+				"/api/v1/indexerstatus" => r#"[
+					{"id": 1, "indexerId": 3, "disabledTill": "2999-01-01T00:00:00Z", "mostRecentFailure": "2024-01-01T00:00:00Z", "initialFailure": "2024-01-01T00:00:00Z"},
+					{"id": 2, "indexerId": 1, "disabledTill": "2000-01-01T00:00:00Z"}
+				]"#,
+				"/api/v1/tag" => r#"[{"id": 1, "label": "hd"}]"#,
+				_ => return Response::new(404, "")
+			};
+			Response::new(200, body).header("Content-Type", "application/json")
+		}).await;
+
+		let prowlarr = Prowlarr::new(&base_url, "key").unwrap();
+		let indexers = prowlarr.indexers().await.unwrap();
+		assert_eq!(indexers.len(), 4);
+		assert_eq!(indexers[1].protocol, Protocol::Usenet);
+		assert_eq!(indexers[2].privacy, Some(Privacy::SemiPrivate));
+		assert_eq!(indexers[1].tags, [1]);
+		assert!(!indexers[3].enabled);
+		assert_eq!(prowlarr.tags().await.unwrap(), [Tag{id: 1, label: "hd".to_string()}]);
+
+		let statuses = prowlarr.statuses().await.unwrap();
+		assert!(statuses[0].is_disabled());
+		assert!(!statuses[1].is_disabled());
+
+		let clients = prowlarr.clients().await.unwrap();
+		assert_eq!(clients.iter().map(|(indexer, _)| indexer.id).collect::<Vec<_>>(), [2, 1]);
+		assert_eq!(prowlarr.endpoint(2).as_str(), format!("{}/2/api", base_url));
+
+		assert!(Prowlarr::new(&base_url, "wrong").unwrap().indexers().await.is_err());
+	}
+
+	#[tokio::test]
+	async fn credentials() {
+		use crate::transport::from_fn;
+
+		let prowlarr = Prowlarr::with_transport("http://localhost:9696/", "", from_fn(|request| {
+			assert_eq!(request.headers, [("X-Api-Key".to_string(), "key".to_string()), ("Authorization".to_string(), "Basic dXNlcjpwYXNz".to_string())]);
+			Ok(crate::transport::Response::new(200, match request.url.path() {
+				"/api/v1/indexer" => r#"[{"id": 1, "protocol": "torrent", "enable": true}]"#,
+				"/api/v1/indexerstatus" => "[]",
+				_ => "<caps />"
+			}))
+		})).unwrap().with_credentials(Credentials::new("key").apikey_header("X-Api-Key").basic_auth("user", "pass"));
+		let clients = prowlarr.clients().await.unwrap();
+		assert_eq!(clients[0].0.id, 1);
+		clients[0].1.caps().await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn body_limit() {
+		use crate::transport::from_fn;
+
+		let prowlarr = Prowlarr::with_transport("http://localhost:9696/", "key", from_fn(|request| {
+			Ok(crate::transport::Response::new(200, vec![b' '; request.max_body_size.unwrap() + 1]))
+		})).unwrap();
+		assert!(matches!(prowlarr.tags().await, Err(Error::BodyTooLarge{limit}) if limit == Limits::default().max_body_size));
+		let prowlarr = prowlarr.with_limits(Limits{max_body_size: 16, ..Limits::default()});
+		assert!(matches!(prowlarr.indexers().await, Err(Error::BodyTooLarge{limit: 16})));
+	}
+}
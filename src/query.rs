@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use smartcow::SmartCow;
+
+use crate::SearchMode;
+
+const DAY: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Parameters for a search.
+pub struct Query {
+	/// The search function (`t`) to use.  If unset, none is sent, and the indexer's default applies.
+	pub mode: Option<SearchMode>,
+	pub q: Option<String>,
+	pub imdbid: Option<String>,
+	pub tmdbid: Option<u32>,
+	pub tvdbid: Option<u32>,
+	pub tvmazeid: Option<u32>,
+	pub season: Option<u32>,
+	pub episode: Option<u32>,
+	pub year: Option<u32>,
+	/// The title being searched for.  Never sent as-is; used to build `q` when an ID search isn't supported.
+	pub title: Option<String>,
+	/// Only return results published at most this long ago.  Torznab's `maxage` has a granularity of days, so this is
+	/// rounded up when sent to the indexer, and results are then filtered again locally.
+	pub max_age: Option<Duration>
+}
+
+impl Query {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn mode(mut self, mode: SearchMode) -> Self {
+		self.mode = Some(mode);
+		self
+	}
+
+	pub fn q(mut self, q: impl ToString) -> Self {
+		self.q = Some(q.to_string());
+		self
+	}
+
+	pub fn imdbid(mut self, imdbid: impl ToString) -> Self {
+		self.imdbid = Some(imdbid.to_string());
+		self
+	}
+
+	pub fn tmdbid(mut self, tmdbid: u32) -> Self {
+		self.tmdbid = Some(tmdbid);
+		self
+	}
+
+	pub fn tvdbid(mut self, tvdbid: u32) -> Self {
+		self.tvdbid = Some(tvdbid);
+		self
+	}
+
+	pub fn tvmazeid(mut self, tvmazeid: u32) -> Self {
+		self.tvmazeid = Some(tvmazeid);
+		self
+	}
+
+	pub fn season(mut self, season: u32) -> Self {
+		self.season = Some(season);
+		self
+	}
+
+	pub fn episode(mut self, episode: u32) -> Self {
+		self.episode = Some(episode);
+		self
+	}
+
+	pub fn year(mut self, year: u32) -> Self {
+		self.year = Some(year);
+		self
+	}
+
+	pub fn title(mut self, title: impl ToString) -> Self {
+		self.title = Some(title.to_string());
+		self
+	}
+
+	pub fn max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = Some(max_age);
+		self
+	}
+
+	pub(crate) fn qparams(&self) -> Vec<(&'static str, SmartCow<'_>)> {
+		let mut qparams = Vec::new();
+		if let Some(mode) = self.mode {
+			qparams.push(("t", SmartCow::Borrowed(mode.as_str())));
+		}
+		if let Some(q) = &self.q {
+			qparams.push(("q", SmartCow::Borrowed(q.as_str())));
+		}
+		if let Some(imdbid) = &self.imdbid {
+			qparams.push(("imdbid", SmartCow::Borrowed(imdbid.as_str())));
+		}
+		let numbers = [("tmdbid", self.tmdbid), ("tvdbid", self.tvdbid), ("tvmazeid", self.tvmazeid), ("season", self.season), ("ep", self.episode), ("year", self.year)];
+		for (key, value) in IntoIterator::into_iter(numbers) {
+			if let Some(value) = value {
+				qparams.push((key, SmartCow::Owned(value.to_string().into())));
+			}
+		}
+		if let Some(max_age) = self.max_age {
+			let days = max_age.as_secs().div_ceil(DAY);
+			qparams.push(("maxage", SmartCow::Owned(days.max(1).to_string().into())));
+		}
+		qparams
+	}
+}
+
+impl From<Option<&str>> for Query {
+	fn from(q: Option<&str>) -> Self {
+		Self{q: q.map(str::to_string), ..Self::default()}
+	}
+}
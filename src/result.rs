@@ -0,0 +1,331 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::FixedOffset;
+use chrono::Utc;
+
+#[cfg(feature = "require-parse-names")]
+use std::convert::TryFrom;
+
+#[cfg(any(feature = "parse-names", feature = "require-parse-names"))]
+use crate::Metadata;
+use crate::Error;
+use crate::Compliance;
+use crate::SeedRequirements;
+use crate::Torrent;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Controls how feed items that don't fully conform to the Torznab spec are handled.
+pub enum ParseMode {
+	/// Any missing or malformed field fails the whole item, except for optional metadata:  dates, grabs, volume
+	/// factors and seed requirements that won't parse are dropped, with a [`Warning`], in either mode.
+	#[default]
+	Strict,
+	/// Fill in what can be recovered, falling back to alternate sources where the spec'd one is missing or broken,
+	/// and attach a [`Warning`] to the result for each problem encountered.
+	Lenient
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The kind of problem a [`Warning`] describes.
+pub enum WarningKind {
+	/// The field was absent.
+	Missing,
+	/// The field was present, but its value couldn't be parsed.
+	Invalid,
+	/// The field's value was corrected; [`Warning::value`] is the original.
+	Repaired
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A problem encountered while parsing a single item, leniently or in optional metadata.
+pub struct Warning {
+	/// GUID of the item the problem was found in, if it had one.
+	pub guid: Option<String>,
+	/// Name of the field that was missing or malformed.
+	pub field: &'static str,
+	/// The raw value as it appeared in the feed, if there was one.
+	pub value: Option<String>,
+	pub kind: WarningKind,
+	/// Where the value was eventually taken from instead, if any fallback source succeeded.
+	pub recovered_from: Option<&'static str>
+}
+
+impl fmt::Display for Warning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "item {}: ", self.guid.as_deref().unwrap_or("<no guid>"))?;
+		match self.kind {
+			WarningKind::Missing => write!(f, "missing `{}`", self.field)?,
+			WarningKind::Invalid => write!(f, "invalid `{}` ({:?})", self.field, self.value.as_deref().unwrap_or(""))?,
+			WarningKind::Repaired => write!(f, "repaired `{}` ({:?})", self.field, self.value.as_deref().unwrap_or(""))?
+		}
+		if let Some(source) = self.recovered_from {
+			write!(f, ", recovered from {}", source)?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// How a result's payload is distributed.
+pub enum Protocol {
+	/// BitTorrent, as served by Torznab indexers.
+	#[default]
+	Torrent,
+	/// Usenet, as served by Newznab indexers; results link to NZB files.
+	Usenet
+}
+
+impl FromStr for Protocol {
+	type Err = Error;
+	fn from_str(s: &str) -> Result<Self, Error> {
+		match s.to_ascii_lowercase().as_str() {
+			"torrent" | "torznab" => Ok(Self::Torrent),
+			"usenet" | "newznab" => Ok(Self::Usenet),
+			_ => Err(Error::InvalidValue("protocol"))
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Newznab's `password` attr, describing whether a release's archives can be extracted.
+pub enum PasswordStatus {
+	None,
+	/// The archive is passworded.
+	Passworded,
+	/// The archive contains another archive, which may be passworded.
+	InnerArchive
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Usenet-specific details of a result, from `newznab:attr` elements.
+pub struct Usenet {
+	pub poster: Option<String>,
+	pub groups: Vec<String>,
+	/// When the article was posted, from `usenetdate`.
+	pub posted_at: Option<DateTime<FixedOffset>>,
+	pub password: Option<PasswordStatus>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Who an indexer admits.
+pub enum Privacy {
+	Public,
+	/// Open registration, but otherwise run like a private tracker.
+	SemiPrivate,
+	Private
+}
+
+impl FromStr for Privacy {
+	type Err = Error;
+	fn from_str(s: &str) -> Result<Self, Error> {
+		match s.to_ascii_lowercase().as_str() {
+			"public" => Ok(Self::Public),
+			"semi-private" | "semiprivate" => Ok(Self::SemiPrivate),
+			"private" => Ok(Self::Private),
+			_ => Err(Error::InvalidValue("privacy"))
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+/// The indexer a result came from, as named by an aggregator such as Jackett's `all` endpoint or Prowlarr.
+pub struct IndexerSource {
+	pub id: Option<String>,
+	pub name: Option<String>
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A single result from a Torznab or Newznab feed.
+pub struct SearchResult {
+	/// Detected from the enclosure type, the link, and which attr namespace the item uses.
+	pub protocol: Protocol,
+	pub guid: Option<String>,
+	/// The item's title, with double encoding undone where it can be detected if
+	/// [`ParseOptions::repair_titles`](crate::ParseOptions::repair_titles) is set.
+	pub name: String,
+	#[cfg(all(feature = "parse-names", not(feature = "require-parse-names")))]
+	pub metadata: Option<Metadata>,
+	#[cfg(feature = "require-parse-names")]
+	pub metadata: Metadata,
+	/// Size in bytes; `0` if it couldn't be determined in [lenient](ParseMode::Lenient) mode.
+	pub size: u64,
+	pub categories: Vec<u32>,
+	pub link: String,
+	/// `None` if the indexer didn't report a count, or reported it as unknown.
+	pub seeders: Option<u32>,
+	/// From the `leechers` attr if present, otherwise derived from `peers` and `seeders`.
+	pub leechers: Option<u32>,
+	/// Seeders plus leechers.
+	pub peers: Option<u32>,
+	pub minimum_ratio: Option<f32>,
+	pub minimum_seedtime: Option<Duration>,
+	/// Fraction of the downloaded amount the tracker counts against the user's ratio; `0` is freeleech.
+	pub download_volume_factor: Option<f32>,
+	/// Multiplier the tracker applies to the uploaded amount; `2` is double upload.
+	pub upload_volume_factor: Option<f32>,
+	/// When the release was published, from `pubDate`, or from `usenetdate` if that's missing.
+	pub published_at: Option<DateTime<FixedOffset>>,
+	/// Number of times the release has been downloaded from the indexer.
+	pub grabs: Option<u32>,
+	/// From Jackett's `<jackettindexer>` element, Prowlarr's `<prowlarrindexer>` element, or an `indexer` attr.
+	pub indexer: Option<IndexerSource>,
+	/// Present for [`Protocol::Usenet`] results only.
+	pub usenet: Option<Usenet>,
+	/// Problems encountered while parsing this result.  In [strict](ParseMode::Strict) mode, only optional metadata that
+	/// was dropped.
+	pub warnings: Vec<Warning>
+}
+
+impl SearchResult {
+	/// Time elapsed since the release was published, or `None` if the publication date is unknown.  Releases dated in
+	/// the future are zero seconds old.
+	pub fn age(&self) -> Option<Duration> {
+		self.age_at(Utc::now())
+	}
+
+	/// Like [`age()`](Self::age), but relative to `now` rather than the current time.
+	pub fn age_at(&self, now: DateTime<Utc>) -> Option<Duration> {
+		self.published_at.map(|published_at| (now - published_at.with_timezone(&Utc)).to_std().unwrap_or_default())
+	}
+
+	/// Whether downloading this release is free, ratio-wise.
+	pub fn is_freeleech(&self) -> bool {
+		self.download_volume_factor == Some(0.0)
+	}
+
+	/// Whether only half of the downloaded amount counts against the user's ratio.
+	pub fn is_halfleech(&self) -> bool {
+		self.download_volume_factor == Some(0.5)
+	}
+
+	/// Whether uploads of this release count at least double.
+	pub fn is_double_upload(&self) -> bool {
+		self.upload_volume_factor.map(|factor| factor >= 2.0).unwrap_or(false)
+	}
+
+	/// Number of bytes that downloading this release will count against the user's ratio.  Releases without a download
+	/// volume factor are assumed to count in full.
+	pub fn ratio_cost(&self) -> u64 {
+		(self.size as f64 * f64::from(self.download_volume_factor.unwrap_or(1.0))) as u64
+	}
+
+	pub fn seed_requirements(&self) -> SeedRequirements {
+		SeedRequirements{minimum_ratio: self.minimum_ratio, minimum_seedtime: self.minimum_seedtime}
+	}
+
+	/// Checks a grab of this release that has so far uploaded `uploaded` bytes (the raw amount, as the torrent client
+	/// counts it) and seeded for `seeded` against the tracker's hit-and-run rules, crediting the release's volume
+	/// factors; see [`SeedRequirements::check_with_factors()`].
+	pub fn compliance(&self, uploaded: u64, seeded: Duration) -> Compliance {
+		let factor = |factor: Option<f32>| factor.unwrap_or(1.0);
+		self.seed_requirements().check_with_factors(self.size, uploaded, seeded, factor(self.download_volume_factor), factor(self.upload_volume_factor))
+	}
+
+	/// Whether the release is known to be older than `max_age`; releases of unknown age are not.
+	pub fn is_older_than(&self, max_age: Duration) -> bool {
+		self.age().map(|age| age > max_age).unwrap_or(false)
+	}
+
+	/// Converts this result to a [`Torrent`], dropping anything `Torrent` has no room for.  Swarm counts that don't fit
+	/// in a `u16` are clamped.
+	#[cfg(not(feature = "require-parse-names"))]
+	pub fn to_torrent(&self) -> Torrent {
+		Torrent::new(self.name.clone(), self.size, self.categories.clone(), self.link.clone(), clamp_count(self.seeders), clamp_count(self.leechers), self.minimum_ratio, self.minimum_seedtime)
+	}
+
+	/// Converts this result to a [`Torrent`], dropping anything `Torrent` has no room for.  Swarm counts that don't fit
+	/// in a `u16` are clamped.
+	#[cfg(feature = "require-parse-names")]
+	pub fn to_torrent(&self) -> Result<Torrent, Error> {
+		Ok(Torrent::new(self.name.clone(), self.size, self.categories.clone(), self.link.clone(), clamp_count(self.seeders), clamp_count(self.leechers), self.minimum_ratio, self.minimum_seedtime)?)
+	}
+}
+
+fn clamp_count(count: Option<u32>) -> Option<u16> {
+	count.map(|count| count.min(u32::from(u16::MAX)) as u16)
+}
+
+#[cfg(not(feature = "require-parse-names"))]
+impl From<SearchResult> for Torrent {
+	fn from(result: SearchResult) -> Self {
+		result.to_torrent()
+	}
+}
+
+#[cfg(feature = "require-parse-names")]
+impl TryFrom<SearchResult> for Torrent {
+	type Error = Error;
+	fn try_from(result: SearchResult) -> Result<Self, Error> {
+		result.to_torrent()
+	}
+}
+
+/// Sorts results newest first; results with no publication date go last.
+pub fn sort_newest_first(results: &mut [SearchResult]) {
+	results.sort_by_key(|result| std::cmp::Reverse(result.published_at));
+}
+
+/// Keeps only freeleech results.
+pub fn retain_freeleech(results: &mut Vec<SearchResult>) {
+	results.retain(SearchResult::is_freeleech);
+}
+
+/// Sorts results cheapest first by download volume factor, then by upload volume factor, highest first.  The sort is
+/// stable, so it can be applied after another to use volume factors as the primary key.
+pub fn sort_freeleech_first(results: &mut [SearchResult]) {
+	results.sort_by(|a, b| {
+		let download = |result: &SearchResult| result.download_volume_factor.unwrap_or(1.0);
+		let upload = |result: &SearchResult| result.upload_volume_factor.unwrap_or(1.0);
+		download(a).total_cmp(&download(b)).then_with(|| upload(b).total_cmp(&upload(a)))
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn freeleech_first() {
+		let result = |name: &str, download, upload| SearchResult{
+			name: name.to_string(),
+			size: 1000,
+			download_volume_factor: download,
+			upload_volume_factor: upload,
+			..SearchResult::default()
+		};
+		let mut results = vec![
+			result("full", None, None),
+			result("half", Some(0.5), Some(1.0)),
+			result("free", Some(0.0), Some(1.0)),
+			result("free-double", Some(0.0), Some(2.0))
+		];
+		sort_freeleech_first(&mut results);
+		assert_eq!(results.iter().map(|result| result.name.as_str()).collect::<Vec<_>>(), ["free-double", "free", "half", "full"]);
+		assert!(results[1].is_freeleech() && !results[1].is_double_upload() && results[1].ratio_cost() == 0);
+		assert!(results[0].is_double_upload() && results[3].ratio_cost() == 1000);
+		assert_eq!(results[2].ratio_cost(), 500);
+		assert!(results[2].is_halfleech());
+		retain_freeleech(&mut results);
+		assert_eq!(results.len(), 2);
+	}
+
+	#[test]
+	fn newest_first() {
+		let result = |name: &str, published_at: Option<&str>| SearchResult{
+			name: name.to_string(),
+			published_at: published_at.map(|date| DateTime::parse_from_rfc3339(date).unwrap()),
+			..SearchResult::default()
+		};
+		let mut results = vec![
+			result("undated", None),
+			result("older", Some("2021-04-02T05:51:01-04:00")),
+			result("newest", Some("2021-04-03T05:29:49-04:00")),
+			result("same instant", Some("2021-04-02T09:51:01Z"))
+		];
+		sort_newest_first(&mut results);
+		assert_eq!(results.iter().map(|result| result.name.as_str()).collect::<Vec<_>>(), ["newest", "older", "same instant", "undated"]);
+	}
+}
@@ -0,0 +1,396 @@
+//! A streaming reader for Torznab and Newznab feeds.  Items are parsed as they're read, straight into
+//! [`SearchResult`]s, so a large feed never needs to be held in memory as a document.
+//!
+//! Reading is bounded by [`Limits`].  Entities declared in a document's DTD are never expanded; references to them
+//! are dropped from text, and fail the feed in attribute values.
+
+use std::io::BufRead;
+use std::io::Take;
+
+use quick_xml::events::attributes::Attribute;
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use quick_xml::XmlVersion;
+
+use crate::caps::indexer_error;
+use crate::feed::Attr;
+use crate::feed::Enclosure;
+use crate::feed::FeedItem;
+use crate::feed::Namespace;
+use crate::torrent;
+use crate::xml::element_text_into;
+use crate::ChannelInfo;
+use crate::Error;
+use crate::IndexerSource;
+use crate::Limits;
+use crate::ParseOptions;
+use crate::SearchResult;
+
+/// Where in the document the reader is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Position {
+	Rss,
+	Channel,
+	Done
+}
+
+/// Reads a feed item by item; see [`ParseOptions::stream()`].
+///
+/// Each item is yielded as a result, as in [`Feed::results`](crate::Feed::results).  Malformed XML or an exceeded
+/// [limit](Limits) ends the stream with an error, after the items before it have been yielded.
+pub struct FeedReader<R> {
+	/// Takes one byte more than [`Limits::max_body_size`], so that reading that byte shows the limit was exceeded.
+	reader: Reader<Take<R>>,
+	options: ParseOptions,
+	channel: ChannelInfo,
+	/// Precedence of the `response` element `channel`'s paging information came from; see [`response_precedence()`].
+	response_rank: u8,
+	position: Position,
+	items: usize,
+	buf: Vec<u8>,
+	/// For reading an item's children, while `buf` holds its start tag.
+	item_buf: Vec<u8>,
+	/// For reading within an element, while `buf` or `item_buf` holds its start tag.
+	scratch: Vec<u8>
+}
+
+/// `newznab:response` is preferred over `torznab:response`.
+fn response_precedence(namespace: Namespace) -> u8 {
+	match namespace {
+		Namespace::Newznab => 0,
+		Namespace::Torznab => 1
+	}
+}
+
+/// Replaces whatever `result` is with [`Error::BodyTooLarge`] if `reader` has gone past the limit, since running into
+/// it can surface as anything from a syntax error to an early end of the document.
+fn check_size<R, T>(reader: &Reader<Take<R>>, limits: &Limits, result: Result<T, Error>) -> Result<T, Error> {
+	if(reader.get_ref().limit() == 0) {
+		return Err(Error::BodyTooLarge{limit: limits.max_body_size});
+	}
+	result
+}
+
+impl<R: BufRead> FeedReader<R> {
+	/// Reads up to the document's `<rss>` element, failing with [`Error::Indexer`] if the indexer responded with an
+	/// `<error>` instead.
+	pub(crate) fn new(reader: R, options: ParseOptions) -> Result<Self, Error> {
+		let mut reader = Reader::from_reader(reader.take((options.limits.max_body_size as u64).saturating_add(1)));
+		reader.config_mut().expand_empty_elements = true;
+		let mut buf = Vec::new();
+		let root = read_root(&mut reader, &mut buf);
+		check_size(&reader, &options.limits, root)?;
+		Ok(Self{
+			reader,
+			options,
+			channel: ChannelInfo::default(),
+			response_rank: u8::MAX,
+			position: Position::Rss,
+			items: 0,
+			buf,
+			item_buf: Vec::new(),
+			scratch: Vec::new()
+		})
+	}
+
+	/// Channel metadata read so far.  Indexers put it ahead of the items, so it's complete once the first item has
+	/// been read.
+	pub fn channel(&self) -> &ChannelInfo {
+		&self.channel
+	}
+
+	/// Reads the rest of the feed into a [`Feed`](crate::Feed).
+	pub fn into_feed(mut self) -> Result<crate::Feed, Error> {
+		let mut results = Vec::new();
+		while let Some(item) = self.next_item()? {
+			results.push(torrent::from_item(item, self.options));
+		}
+		Ok(crate::Feed{channel: self.channel, results})
+	}
+
+	/// Reads up to and including the next item, or `None` at the end of the feed.
+	fn next_item(&mut self) -> Result<Option<FeedItem>, Error> {
+		let item = self.read_next_item();
+		check_size(&self.reader, &self.options.limits, item)
+	}
+
+	fn read_next_item(&mut self) -> Result<Option<FeedItem>, Error> {
+		let limits = &self.options.limits;
+		while(self.position != Position::Done) {
+			self.buf.clear();
+			match self.reader.read_event_into(&mut self.buf)? {
+				Event::Start(element) => match (self.position, element.name().as_ref()) {
+					(_, b"item") => {
+						if(self.items == limits.max_items) {
+							return Err(Error::TooManyItems{limit: limits.max_items});
+						}
+						self.items += 1;
+						return read_item(&mut self.reader, limits, &mut self.item_buf, &mut self.scratch).map(Some);
+					},
+					(Position::Rss, b"channel") => self.position = Position::Channel,
+					(Position::Channel, _) => read_channel_element(&mut self.reader, limits, &element, &mut self.channel, &mut self.response_rank, &mut self.scratch)?,
+					_ => self.reader.read_to_end_into(element.name(), &mut self.scratch).map(|_| ())?
+				},
+				Event::End(_) => self.position = match self.position {
+					Position::Channel => Position::Rss,
+					_ => Position::Done
+				},
+				Event::Eof => self.position = Position::Done,
+				_ => {}
+			}
+		}
+		Ok(None)
+	}
+}
+
+impl<R: BufRead> Iterator for FeedReader<R> {
+	type Item = Result<SearchResult, Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		match self.next_item() {
+			Ok(item) => item.map(|item| torrent::from_item(item, self.options)),
+			Err(e) => {
+				self.position = Position::Done;
+				Some(Err(e))
+			}
+		}
+	}
+}
+
+/// Reads up to and including the root element's start tag.
+fn read_root<R: BufRead>(reader: &mut Reader<R>, buf: &mut Vec<u8>) -> Result<(), Error> {
+	loop {
+		buf.clear();
+		match reader.read_event_into(buf)? {
+			Event::Start(element) => return match element.name().as_ref() {
+				b"rss" | b"rdf:RDF" => Ok(()),
+				b"error" => Err(indexer_error(reader, &element)?),
+				_ => Err(Error::NotAFeed)
+			},
+			Event::Eof => return Err(Error::UnexpectedEof),
+			_ => {}
+		}
+	}
+}
+
+/// Decoded value of `attr`, failing if it's longer than [`Limits::max_string_length`].
+fn attr_value<R: BufRead>(reader: &Reader<R>, limits: &Limits, attr: &Attribute<'_>) -> Result<String, Error> {
+	if(attr.value.len() > limits.max_string_length) {
+		return Err(Error::StringTooLong{limit: limits.max_string_length});
+	}
+	Ok(attr.decoded_and_normalized_value(XmlVersion::Implicit1_0, reader.decoder())?.into_owned())
+}
+
+fn read_channel_element<R: BufRead>(reader: &mut Reader<R>, limits: &Limits, element: &BytesStart<'_>, channel: &mut ChannelInfo, response_rank: &mut u8, buf: &mut Vec<u8>) -> Result<(), Error> {
+	let max_len = limits.max_string_length;
+	match element.name().as_ref() {
+		b"title" => channel.title = element_text_into(reader, buf, max_len)?.unwrap_or_default(),
+		b"link" => channel.link = element_text_into(reader, buf, max_len)?.unwrap_or_default(),
+		b"description" => channel.description = element_text_into(reader, buf, max_len)?.unwrap_or_default(),
+		b"language" => channel.language = element_text_into(reader, buf, max_len)?,
+		_ => {
+			let name = element.name();
+			let namespace = name.prefix().and_then(|prefix| Namespace::from_prefix(prefix.as_ref()));
+			if let Some(namespace) = namespace.filter(|namespace| name.local_name().as_ref() == b"response" && response_precedence(*namespace) < *response_rank) {
+				*response_rank = response_precedence(namespace);
+				channel.offset = None;
+				channel.total = None;
+				for attr in element.attributes() {
+					let attr = attr.map_err(quick_xml::Error::from)?;
+					match attr.key.as_ref() {
+						b"offset" => channel.offset = attr_value(reader, limits, &attr)?.trim().parse().ok(),
+						b"total" => channel.total = attr_value(reader, limits, &attr)?.trim().parse().ok(),
+						_ => {}
+					}
+				}
+			}
+			reader.read_to_end_into(name, buf)?;
+		}
+	}
+	Ok(())
+}
+
+/// Reads the children of an `<item>` whose start tag has just been read.
+fn read_item<R: BufRead>(reader: &mut Reader<R>, limits: &Limits, buf: &mut Vec<u8>, scratch: &mut Vec<u8>) -> Result<FeedItem, Error> {
+	let max_len = limits.max_string_length;
+	let mut item = FeedItem::default();
+	let mut attrs = 0;
+	loop {
+		buf.clear();
+		let element = match reader.read_event_into(buf)? {
+			Event::Start(element) => element,
+			Event::End(_) => return Ok(item),
+			Event::Eof => return Err(Error::UnexpectedEof),
+			_ => continue
+		};
+		match element.name().as_ref() {
+			b"title" => item.title = element_text_into(reader, scratch, max_len)?,
+			b"guid" => item.guid = Some(element_text_into(reader, scratch, max_len)?.unwrap_or_default()),
+			b"link" => item.link = element_text_into(reader, scratch, max_len)?,
+			b"pubDate" => item.pub_date = element_text_into(reader, scratch, max_len)?,
+			b"category" => item.categories.push(element_text_into(reader, scratch, max_len)?.unwrap_or_default()),
+			b"size" => item.size = element_text_into(reader, scratch, max_len)?,
+			b"enclosure" => {
+				let mut enclosure = Enclosure::default();
+				for attr in element.attributes() {
+					let attr = attr.map_err(quick_xml::Error::from)?;
+					let field = match attr.key.as_ref() {
+						b"url" => &mut enclosure.url,
+						b"length" => &mut enclosure.length,
+						b"type" => &mut enclosure.mime_type,
+						_ => continue
+					};
+					*field = attr_value(reader, limits, &attr)?;
+				}
+				item.enclosure = Some(enclosure);
+				reader.read_to_end_into(element.name(), scratch)?;
+			},
+			name @ (b"jackettindexer" | b"prowlarrindexer") => {
+				let slot = if(name == b"jackettindexer") { 0 } else { 1 };
+				let mut id = None;
+				for attr in element.attributes() {
+					let attr = attr.map_err(quick_xml::Error::from)?;
+					if(attr.key.as_ref() == b"id") {
+						id = Some(attr_value(reader, limits, &attr)?);
+					}
+				}
+				item.indexers[slot] = Some(IndexerSource{id, name: element_text_into(reader, scratch, max_len)?});
+			},
+			_ => {
+				let name = element.name();
+				if let Some(namespace) = name.prefix().and_then(|prefix| Namespace::from_prefix(prefix.as_ref())) {
+					item.attrs.touch(namespace);
+					if(name.local_name().as_ref() == b"attr") {
+						if(attrs == limits.max_attrs) {
+							return Err(Error::TooManyAttrs{limit: limits.max_attrs});
+						}
+						attrs += 1;
+						read_attr(reader, limits, &element, namespace, &mut item)?;
+					}
+				}
+				reader.read_to_end_into(name, scratch)?;
+			}
+		}
+	}
+}
+
+/// Records a `torznab:attr` or `newznab:attr` if it's one [`torrent::from_item()`] reads.  Values of others aren't
+/// decoded.
+fn read_attr<R: BufRead>(reader: &Reader<R>, limits: &Limits, element: &BytesStart<'_>, namespace: Namespace, item: &mut FeedItem) -> Result<(), Error> {
+	let mut attr = None;
+	let mut value = None;
+	for attribute in element.attributes() {
+		let attribute = attribute.map_err(quick_xml::Error::from)?;
+		match attribute.key.as_ref() {
+			b"name" => match Attr::from_name(&attr_value(reader, limits, &attribute)?) {
+				Some(v) => attr = Some(v),
+				None => return Ok(())
+			},
+			b"value" => value = Some(attribute),
+			_ => {}
+		}
+	}
+	if let (Some(attr), Some(value)) = (attr, value) {
+		item.attrs.push(namespace, attr, attr_value(reader, limits, &value)?);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn items_as_read() {
+		let feed = br#"<?xml version="1.0" encoding="UTF-8"?>
+			<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+			  <channel>
+				<title>Jackett</title>
+				<torznab:response offset="0" total="2" />
+				<item>
+				  <title>First &amp; Best</title>
+				  <link>magnet:?xt=urn:btih:01</link>
+				  <enclosure url="" length="10" type="application/x-bittorrent" />
+				  <torznab:attr name="infohash" value="01" />
+				  <torznab:attr name="seeders" value="3" />
+				  <torznab:attr name="category" value="2000" />
+				  <torznab:attr name="category" value="2040" />
+				</item>
+				<item>
+				  <title>Second</title>
+				  <link>magnet:?xt=urn:btih:02</link>
+				  <enclosure url="" length="20" type="application/x-bittorrent" />
+				</item>
+				<item>
+				  <title>Truncated</title>
+		"#;
+		let mut reader = ParseOptions{mode: crate::ParseMode::Lenient, ..Default::default()}.stream(&feed[..]).unwrap();
+		let first = reader.next().unwrap().unwrap();
+		assert_eq!(reader.channel().title, "Jackett");
+		assert_eq!(reader.channel().total, Some(2));
+		assert_eq!((first.name.as_str(), first.size, first.seeders), ("First & Best", 10, Some(3)));
+		assert_eq!(first.categories, vec![2000, 2040]);
+		assert_eq!(reader.next().unwrap().unwrap().name, "Second");
+		assert!(reader.next().unwrap().is_err());
+		assert!(reader.next().is_none());
+
+		assert!(matches!(ParseOptions::default().stream(&b"<html></html>"[..]), Err(Error::NotAFeed)));
+	}
+
+	fn limited(limits: Limits) -> ParseOptions {
+		ParseOptions{mode: crate::ParseMode::Lenient, limits, ..Default::default()}
+	}
+
+	const TWO_ITEMS: &[u8] = br#"<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed"><channel>
+		<item><title>One</title><link>magnet:</link><torznab:attr name="seeders" value="1" /><torznab:attr name="peers" value="2" /></item>
+		<item><title>Two</title><link>magnet:</link></item>
+	</channel></rss>"#;
+
+	#[test]
+	fn limits() {
+		let mut reader = limited(Limits{max_items: 1, ..Default::default()}).stream(TWO_ITEMS).unwrap();
+		assert_eq!(reader.next().unwrap().unwrap().name, "One");
+		assert!(matches!(reader.next(), Some(Err(Error::TooManyItems{limit: 1}))));
+		assert!(reader.next().is_none());
+
+		let err = limited(Limits{max_attrs: 1, ..Default::default()}).parse_feed(TWO_ITEMS).unwrap_err();
+		assert!(matches!(err, Error::TooManyAttrs{limit: 1}));
+		let err = limited(Limits{max_string_length: 2, ..Default::default()}).parse_feed(TWO_ITEMS).unwrap_err();
+		assert!(matches!(err, Error::StringTooLong{limit: 2}));
+		let long_value = br#"<rss><channel><item><title>t</title><enclosure url="http://example.com/0123456789" /></item></channel></rss>"#;
+		let err = limited(Limits{max_string_length: 20, ..Default::default()}).parse_feed(long_value).unwrap_err();
+		assert!(matches!(err, Error::StringTooLong{limit: 20}));
+
+		for limit in [0, 40, TWO_ITEMS.len() - 1] {
+			let err = limited(Limits{max_body_size: limit, ..Default::default()}).parse_feed_reader(TWO_ITEMS).unwrap_err();
+			assert!(matches!(err, Error::BodyTooLarge{limit: l} if l == limit), "{:?}", err);
+		}
+		assert_eq!(limited(Limits{max_body_size: TWO_ITEMS.len(), ..Default::default()}).parse_feed(TWO_ITEMS).unwrap().results.len(), 2);
+		assert_eq!(limited(Limits::unlimited()).parse_feed(TWO_ITEMS).unwrap().results.len(), 2);
+	}
+
+	#[test]
+	fn entities_not_expanded() {
+		let laughs = br#"<?xml version="1.0"?>
+			<!DOCTYPE rss [
+			  <!ENTITY lol "lol">
+			  <!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+			  <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+			  <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+			  <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+			  <!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+			  <!ENTITY lol6 "&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;">
+			  <!ENTITY lol7 "&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;">
+			  <!ENTITY lol8 "&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;">
+			  <!ENTITY lol9 "&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;">
+			]>
+			<rss><channel><title>&lol9;</title><item><title>Laughs &lol9; &amp; more</title><link>magnet:</link></item></channel></rss>"#;
+		let feed = limited(Limits::default()).parse_feed(laughs).unwrap();
+		assert_eq!(feed.channel.title, "");
+		assert_eq!(feed.results[0].as_ref().unwrap().name, "Laughs  & more");
+
+		let in_attr = br#"<!DOCTYPE rss [<!ENTITY lol "lol">]><rss><channel><item><title>t</title><enclosure url="&lol;" /></item></channel></rss>"#;
+		assert!(limited(Limits::default()).parse_feed(in_attr).is_err());
+	}
+}
@@ -0,0 +1,155 @@
+//! Metrics recorded through the [`metrics`](https://docs.rs/metrics) facade when the `metrics` feature is enabled; an
+//! exporter such as `metrics-exporter-prometheus` must be installed by the application to collect them.  Without the
+//! feature, these functions do nothing.
+//!
+//! | Metric | Type | Labels |
+//! |-|-|-|
+//! | `torznab_requests_total` | counter | `indexer`, `mode`, `status` |
+//! | `torznab_request_duration_seconds` | histogram | `indexer`, `mode` |
+//! | `torznab_response_bytes_total` | counter | `indexer` |
+//! | `torznab_parse_failures_total` | counter | `indexer`, `field` |
+//! | `torznab_parse_warnings_total` | counter | `indexer`, `field` |
+//! | `torznab_items_total` | counter | `indexer` |
+//! | `torznab_caps_cache_total` | counter | `indexer`, `result` (`hit` or `miss`) |
+//! | `torznab_rate_limited_total` | counter | `indexer` |
+//! | `torznab_backoff_seconds` | histogram | `indexer`, `source` (`retry_after` or `circuit`) |
+
+#![cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use crate::transport::TransportErrorKind;
+use crate::Error;
+
+#[cfg(feature = "metrics")]
+/// Label for a request's outcome:  `ok`, the HTTP status code, a short description of the failure, or `parse` for any
+/// of the errors in reading a response.
+fn status(result: Result<(), &Error>) -> String {
+	let err = match result {
+		Ok(()) => return "ok".to_string(),
+		Err(err) => err
+	};
+	match err {
+		Error::Status(status) => return status.to_string(),
+		Error::Transport(err) => match err.kind {
+			TransportErrorKind::Connect => "connect",
+			TransportErrorKind::Timeout => "timeout",
+			TransportErrorKind::Body => "body",
+			TransportErrorKind::BodyTooLarge => "too_large",
+			TransportErrorKind::Other => "http"
+		},
+		Error::BodyTooLarge{..} => "too_large",
+		Error::TooManyItems{..} => "too_many_items",
+		Error::TooManyAttrs{..} => "too_many_attrs",
+		Error::StringTooLong{..} => "string_too_long",
+		Error::Credentials(_) => "credentials",
+		Error::CircuitOpen{..} => "circuit_open",
+		Error::Indexer{..} => "indexer_error",
+		Error::InvalidUrl(_) => "invalid_url",
+		Error::InvalidFilter(_) => "invalid_filter",
+		Error::UnsupportedScheme(_) => "unsupported_scheme",
+		Error::UnsupportedMode(_) => "unsupported_mode",
+		Error::UnsupportedParam(_) => "unsupported_param",
+		Error::Io(_) => "io",
+		Error::NzbSizeMismatch{..} => "nzb_size_mismatch",
+		Error::MissingField(_) |
+		Error::Json(_) |
+		Error::NotAFeed |
+		Error::UnexpectedEof |
+		Error::Xml(_) |
+		Error::Item{..} |
+		Error::MissingTitle |
+		Error::MissingSize |
+		Error::MissingLink |
+		Error::InvalidValue(_) |
+		Error::EmptyExtension(_) |
+		Error::ParseInt(_) |
+		Error::ParseFloat(_) |
+		Error::ParseDate(_) => "parse",
+		#[cfg(feature = "require-parse-names")]
+		Error::ParseTorrentName(_) => "parse"
+	}.to_string()
+}
+
+pub(crate) fn request(indexer: &str, mode: &'static str, result: Result<(), &Error>, duration: Duration) {
+	#[cfg(feature = "metrics")]
+	{
+		if let Err(Error::Status(429)) = result {
+			metrics::counter!("torznab_rate_limited_total", "indexer" => indexer.to_string()).increment(1);
+		}
+		metrics::counter!("torznab_requests_total", "indexer" => indexer.to_string(), "mode" => mode, "status" => status(result)).increment(1);
+		metrics::histogram!("torznab_request_duration_seconds", "indexer" => indexer.to_string(), "mode" => mode).record(duration.as_secs_f64());
+	}
+}
+
+/// Records a wait imposed on requests to `indexer`, by its `Retry-After` header or by the client's circuit breaker.
+pub(crate) fn backoff(indexer: &str, source: &'static str, wait: Duration) {
+	#[cfg(feature = "metrics")]
+	metrics::histogram!("torznab_backoff_seconds", "indexer" => indexer.to_string(), "source" => source).record(wait.as_secs_f64());
+}
+
+pub(crate) fn bytes_received(indexer: &str, bytes: usize) {
+	#[cfg(feature = "metrics")]
+	metrics::counter!("torznab_response_bytes_total", "indexer" => indexer.to_string()).increment(bytes as u64);
+}
+
+pub(crate) fn results<'a>(indexer: &str, results: impl IntoIterator<Item = &'a Result<crate::SearchResult, Error>>) {
+	#[cfg(feature = "metrics")]
+	{
+		let mut items = 0;
+		for result in results {
+			match result {
+				Ok(result) => {
+					items += 1;
+					for warning in &result.warnings {
+						metrics::counter!("torznab_parse_warnings_total", "indexer" => indexer.to_string(), "field" => warning.field).increment(1);
+					}
+				},
+				Err(Error::Item{field, ..}) => metrics::counter!("torznab_parse_failures_total", "indexer" => indexer.to_string(), "field" => *field).increment(1),
+				Err(_) => metrics::counter!("torznab_parse_failures_total", "indexer" => indexer.to_string(), "field" => "").increment(1)
+			}
+		}
+		metrics::counter!("torznab_items_total", "indexer" => indexer.to_string()).increment(items);
+	}
+}
+
+pub(crate) fn caps_cache(indexer: &str, hit: bool) {
+	#[cfg(feature = "metrics")]
+	metrics::counter!("torznab_caps_cache_total", "indexer" => indexer.to_string(), "result" => if(hit) { "hit" } else { "miss" }).increment(1);
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+	use metrics_util::debugging::DebugValue;
+	use metrics_util::debugging::DebuggingRecorder;
+
+	use super::*;
+
+	#[test]
+	fn recorded() {
+		let recorder = DebuggingRecorder::new();
+		let snapshotter = recorder.snapshotter();
+		metrics::with_local_recorder(&recorder, || {
+			request("rarbg", "search", Ok(()), Duration::from_millis(250));
+			request("rarbg", "search", Err(&Error::Indexer{code: None, description: String::new()}), Duration::from_millis(50));
+			request("rarbg", "search", Err(&Error::UnsupportedParam("imdbid")), Duration::from_millis(1));
+			request("rarbg", "search", Err(&Error::NotAFeed), Duration::from_millis(1));
+			backoff("rarbg", "retry_after", Duration::from_secs(60));
+			bytes_received("rarbg", 1024);
+			caps_cache("rarbg", true);
+		});
+		let metrics: Vec<_> = snapshotter.snapshot().into_vec().into_iter()
+			.map(|(key, _, _, value)| (key.key().name().to_string(), key.key().labels().map(|label| label.value().to_string()).collect::<Vec<_>>(), value))
+			.collect();
+		let find = |name: &str, labels: &[&str]| metrics.iter().find(|(n, l, _)| n == name && l == labels).map(|(_, _, value)| value);
+		assert_eq!(find("torznab_requests_total", &["rarbg", "search", "ok"]), Some(&DebugValue::Counter(1)));
+		assert_eq!(find("torznab_requests_total", &["rarbg", "search", "indexer_error"]), Some(&DebugValue::Counter(1)));
+		assert_eq!(find("torznab_requests_total", &["rarbg", "search", "unsupported_param"]), Some(&DebugValue::Counter(1)));
+		assert_eq!(find("torznab_requests_total", &["rarbg", "search", "parse"]), Some(&DebugValue::Counter(1)));
+		assert_eq!(find("torznab_response_bytes_total", &["rarbg"]), Some(&DebugValue::Counter(1024)));
+		assert!(matches!(find("torznab_backoff_seconds", &["rarbg", "retry_after"]), Some(DebugValue::Histogram(samples)) if samples.len() == 1));
+		assert_eq!(find("torznab_caps_cache_total", &["rarbg", "hit"]), Some(&DebugValue::Counter(1)));
+		assert!(matches!(find("torznab_request_duration_seconds", &["rarbg", "search"]), Some(DebugValue::Histogram(samples)) if samples.len() == 4));
+	}
+}
@@ -0,0 +1,135 @@
+//! A minimal HTTP/1.1 server for tests that need to talk to a stand-in indexer.
+
+use std::sync::Arc;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Request {
+	/// Path and query string, as sent.
+	pub target: String,
+	pub headers: Vec<(String, String)>
+}
+
+impl Request {
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+	}
+
+	pub fn path(&self) -> &str {
+		self.target.split('?').next().unwrap_or_default()
+	}
+
+	pub fn query(&self, key: &str) -> Option<String> {
+		let url = url::Url::parse(&format!("http://localhost{}", self.target)).ok()?;
+		url.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.into_owned())
+	}
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Response {
+	pub status: u16,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>
+}
+
+impl Response {
+	pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+		Self{status, headers: Vec::new(), body: body.into()}
+	}
+
+	pub fn header(mut self, name: &str, value: &str) -> Self {
+		self.headers.push((name.to_string(), value.to_string()));
+		self
+	}
+}
+
+type Handler = dyn Fn(Request) -> Response + Send + Sync;
+
+/// Serves `handler` on an ephemeral local port until the test's runtime shuts down; returns `http://127.0.0.1:{port}`.
+pub(crate) async fn serve(handler: impl Fn(Request) -> Response + Send + Sync + 'static) -> String {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let address = listener.local_addr().unwrap();
+	let handler: Arc<Handler> = Arc::new(handler);
+	tokio::spawn(async move {
+		while let Ok((stream, _)) = listener.accept().await {
+			tokio::spawn(respond(stream, handler.clone()));
+		}
+	});
+	format!("http://{}", address)
+}
+
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+/// Like [`serve()`], but over TLS as configured by `config`; returns `https://localhost:{port}`.  Handshakes that fail
+/// are dropped.
+pub(crate) async fn serve_tls(config: tokio_rustls::rustls::ServerConfig, handler: impl Fn(Request) -> Response + Send + Sync + 'static) -> String {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let address = listener.local_addr().unwrap();
+	let handler: Arc<Handler> = Arc::new(handler);
+	let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+	tokio::spawn(async move {
+		while let Ok((stream, _)) = listener.accept().await {
+			let (acceptor, handler) = (acceptor.clone(), handler.clone());
+			tokio::spawn(async move {
+				if let Ok(stream) = acceptor.accept(stream).await {
+					respond(stream, handler).await;
+				}
+			});
+		}
+	});
+	format!("https://localhost:{}", address.port())
+}
+
+/// Like [`serve()`], but on a Unix socket in the temporary directory; returns the socket's path.
+#[cfg(all(unix, feature = "unix-socket"))]
+pub(crate) async fn serve_unix(handler: impl Fn(Request) -> Response + Send + Sync + 'static) -> std::path::PathBuf {
+	use std::sync::atomic::AtomicUsize;
+	use std::sync::atomic::Ordering;
+
+	static NEXT: AtomicUsize = AtomicUsize::new(0);
+	let path = std::env::temp_dir().join(format!("torznab-test-{}-{}.sock", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+	let _ = std::fs::remove_file(&path);
+	let listener = tokio::net::UnixListener::bind(&path).unwrap();
+	let handler: Arc<Handler> = Arc::new(handler);
+	tokio::spawn(async move {
+		while let Ok((stream, _)) = listener.accept().await {
+			tokio::spawn(respond(stream, handler.clone()));
+		}
+	});
+	path
+}
+
+/// Reads one request from `stream` and answers it with `handler`.
+async fn respond(mut stream: impl AsyncRead + AsyncWrite + Unpin, handler: Arc<Handler>) {
+	let mut buf = Vec::new();
+	let mut chunk = [0u8; 4096];
+	while(!buf.windows(4).any(|window| window == b"\r\n\r\n")) {
+		match stream.read(&mut chunk).await {
+			Ok(0) | Err(_) => return,
+			Ok(n) => buf.extend_from_slice(&chunk[..n])
+		}
+	}
+	let head = String::from_utf8_lossy(&buf).into_owned();
+	let mut lines = head.split("\r\n");
+	let mut request_line = lines.next().unwrap_or_default().split(' ').skip(1);
+	let request = Request{
+		target: request_line.next().unwrap_or_default().to_string(),
+		headers: lines.take_while(|line| !line.is_empty())
+			.filter_map(|line| line.split_once(':'))
+			.map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+			.collect()
+	};
+	let response = handler(request);
+	let mut head = format!("HTTP/1.1 {} Stand-in\r\nContent-Length: {}\r\nConnection: close\r\n", response.status, response.body.len());
+	for (name, value) in &response.headers {
+		head.push_str(&format!("{}: {}\r\n", name, value));
+	}
+	head.push_str("\r\n");
+	let _ = stream.write_all(head.as_bytes()).await;
+	let _ = stream.write_all(&response.body).await;
+	let _ = stream.shutdown().await;
+}
@@ -1,70 +1,369 @@
-extern crate rss;
-
+use core::convert::TryFrom;
 use core::str::FromStr;
-use std::collections::BTreeMap;
 use std::time::Duration;
 
-use rss::extension::Extension;
-use rss::Item;
+use chrono::DateTime;
+use chrono::FixedOffset;
 
+use crate::charset;
+use crate::date::parse_date;
+use crate::feed::Attr;
+use crate::feed::Attrs;
+use crate::feed::FeedItem;
+use crate::feed::Namespace;
 use crate::Error;
-use crate::Torrent;
+use crate::IndexerSource;
+#[cfg(any(feature = "parse-names", feature = "require-parse-names"))]
+use crate::Metadata;
+use crate::ParseMode;
+use crate::ParseOptions;
+use crate::PasswordStatus;
+use crate::Protocol;
+use crate::SearchResult;
+use crate::Usenet;
+use crate::Warning;
+use crate::WarningKind;
+
+/// A problem with the spec'd source of a field.
+struct Issue<'a> {
+	value: Option<&'a str>,
+	kind: WarningKind,
+	err: Error
+}
+
+impl<'a> Issue<'a> {
+	fn missing(err: Error) -> Self {
+		Self{value: None, kind: WarningKind::Missing, err}
+	}
+
+	fn invalid(value: &'a str, err: impl Into<Error>) -> Self {
+		Self{value: Some(value), kind: WarningKind::Invalid, err: err.into()}
+	}
+}
+
+struct Context<'a> {
+	guid: Option<&'a str>,
+	mode: ParseMode,
+	attrs: &'a Attrs,
+	warnings: Vec<Warning>
+}
+
+impl<'a> Context<'a> {
+	fn error(&self, field: &'static str, value: Option<&str>, err: Error) -> Error {
+		Error::Item{
+			guid: self.guid.map(str::to_string),
+			field,
+			value: value.map(str::to_string),
+			source: Box::new(err)
+		}
+	}
+
+	fn warn(&mut self, field: &'static str, value: Option<&str>, kind: WarningKind, recovered_from: Option<&'static str>) {
+		self.warnings.push(Warning{
+			guid: self.guid.map(str::to_string),
+			field,
+			value: value.map(str::to_string),
+			kind,
+			recovered_from
+		});
+	}
+
+	/// Deals with an [`Issue`] in `field`.  In strict mode, this fails the item; in lenient mode, it's
+	/// [`optional()`](Self::optional).
+	fn recover<T>(&mut self, field: &'static str, issue: Issue<'_>, fallback: impl FnOnce(&Self) -> Option<(T, &'static str)>) -> Result<Option<T>, Error> {
+		if(self.mode == ParseMode::Strict) {
+			return Err(self.error(field, issue.value, issue.err));
+		}
+		Ok(self.optional(field, issue, fallback))
+	}
+
+	/// Deals with an [`Issue`] in `field` in any mode, for optional metadata that shouldn't cost the item:  `fallback`
+	/// is given a chance to produce a value (along with a description of where it came from), and a warning is
+	/// recorded.
+	fn optional<T>(&mut self, field: &'static str, issue: Issue<'_>, fallback: impl FnOnce(&Self) -> Option<(T, &'static str)>) -> Option<T> {
+		let recovered = fallback(self);
+		self.warn(field, issue.value, issue.kind, recovered.as_ref().map(|(_, source)| *source));
+		recovered.map(|(v, _)| v)
+	}
+
+	fn attr(&self, key: Attr) -> Option<&'a str> {
+		self.attrs.get(key)
+	}
+
+	fn attrs(&self, key: Attr) -> impl Iterator<Item = &'a str> + 'a {
+		self.attrs.all(key)
+	}
+
+	fn parsed_attr<T>(&mut self, key: Attr) -> Result<Option<T>, Error>
+	where
+		T: FromStr,
+		Error: From<T::Err>
+	{
+		let value = match self.attr(key) {
+			Some(v) => v,
+			None => return Ok(None)
+		};
+		match value.parse::<T>() {
+			Ok(v) => Ok(Some(v)),
+			Err(e) => self.recover(key.name(), Issue::invalid(value, e), |_| None)
+		}
+	}
+
+	/// Parses optional metadata, which is dropped with a warning rather than failing the item if it won't parse.
+	fn optional_attr<T: FromStr>(&mut self, key: Attr) -> Option<T> {
+		let value = self.attr(key)?;
+		match value.parse::<T>() {
+			Ok(v) => Some(v),
+			Err(_) => self.optional(key.name(), Issue::invalid(value, Error::InvalidValue(key.name())), |_| None)
+		}
+	}
+
+	/// Like [`optional_attr()`](Self::optional_attr), for ratios and volume factors, which must be finite and not
+	/// negative.
+	fn factor_attr(&mut self, key: Attr) -> Option<f32> {
+		let factor = self.optional_attr::<f32>(key)?;
+		if(factor.is_finite() && factor >= 0.0) {
+			return Some(factor);
+		}
+		let value = self.attr(key);
+		self.warn(key.name(), value, WarningKind::Invalid, None);
+		None
+	}
+
+	/// Parses a swarm count.  Some indexers report unknown counts as negative numbers, so those become `None`, as do
+	/// counts too large to be plausible.
+	fn count_attr(&mut self, key: Attr) -> Result<Option<u32>, Error> {
+		Ok(self.parsed_attr::<i64>(key)?.and_then(|count| u32::try_from(count).ok()))
+	}
+
+	/// First of the named attrs that is present and parses, for use as a fallback.
+	fn fallback_attr<T: FromStr>(&self, key: Attr, source: &'static str) -> Option<(T, &'static str)> {
+		self.attr(key).and_then(|v| v.parse().ok()).map(|v| (v, source))
+	}
+}
 
-fn get_extension_value<'a>(ext: &'a BTreeMap<String, Vec<Extension>>, key: &str) -> Result<Option<&'a str>, Error> {
-	let ext = match ext.get("attr") {
-		Some(v) => v,
-		None => return Ok(None)
+fn parse_size(item: &FeedItem, ctx: &mut Context<'_>) -> Result<u64, Error> {
+	let issue = match &item.enclosure {
+		Some(enclosure) => match enclosure.length.parse() {
+			Ok(size) => return Ok(size),
+			Err(e) => Issue::invalid(&enclosure.length, e)
+		},
+		None => Issue::missing(Error::MissingSize)
 	};
-	if(ext.is_empty()) {
-		return Err(Error::EmptyExtension(key.to_string()))
-	}
-	for extension in ext.iter() {
-		if let Some(name) = extension.attrs().get("name") {
-			if let Some(value) = extension.attrs().get("value") {
-				if(name == key) {
-					return Ok(Some(value));
-				}
+	let size = ctx.recover("size", issue, |ctx| {
+		ctx.fallback_attr(Attr::Size, "`size` attr").or_else(|| item.size.as_deref()
+			.and_then(|size| size.parse().ok())
+			.map(|size| (size, "`size` element")))
+	})?;
+	Ok(size.unwrap_or(0))
+}
+
+fn parse_categories(item: &FeedItem, ctx: &mut Context<'_>) -> Result<Vec<u32>, Error> {
+	let mut categories = Vec::with_capacity(item.categories.len());
+	for category in &item.categories {
+		match category.parse::<u32>() {
+			Ok(category) => categories.push(category),
+			Err(e) => {
+				ctx.recover::<()>("category", Issue::invalid(category, e), |_| None)?;
 			}
 		}
 	}
-	Ok(None)
+	if(categories.is_empty() && ctx.mode == ParseMode::Lenient) {
+		categories.extend(ctx.attrs(Attr::Category).filter_map(|category| category.parse::<u32>().ok()));
+	}
+	Ok(categories)
 }
 
-fn get_parsed_extension_value<T>(ext: &BTreeMap<String, Vec<Extension>>, key: &str) -> Result<Option<T>, Error>
-where
-	T: FromStr,
-	Error: From<T::Err>
-{
-	match get_extension_value(ext, key)? {
-		Some(v) => Ok(Some(v.parse::<T>()?)),
-		None => Ok(None)
+/// The `usenetdate` attr, which is both the fallback for `pubDate` and a Usenet result's posting date, so is parsed
+/// once for both.
+fn parse_usenetdate(ctx: &mut Context<'_>) -> Option<DateTime<FixedOffset>> {
+	let date = ctx.attr(Attr::UsenetDate)?;
+	match parse_date(date) {
+		Ok(date) => Some(date),
+		Err(e) => ctx.optional("usenetdate", Issue::invalid(date, e), |_| None)
 	}
 }
 
-pub fn from_item(item: Item) -> Result<Torrent, Error> {
-	let name = item.title().ok_or(Error::MissingTitle)?.to_string();
-	let size = item.enclosure().ok_or(Error::MissingSize)?.length().parse()?;
-	let categories = item.categories().iter().map(|category| category.name().parse::<u32>()).collect::<Result<Vec<_>, _>>()?;
-	let link = item.link().ok_or(Error::MissingLink)?.to_string();
-	let mut seeders = None;
-	let mut leechers = None;
-	let mut minimum_ratio = None;
-	let mut minimum_seedtime = None;
-	if let Some(torznab) = item.extensions().get("torznab") {
-		seeders = match get_parsed_extension_value(torznab, "seeders")? {
-			Some(seeders) => {
-				leechers = get_parsed_extension_value::<u16>(torznab, "peers")?.map(|peers| peers - seeders);
-				Some(seeders)
-			},
-			None => None
-		};
-		minimum_ratio = get_parsed_extension_value(torznab, "minimumrato")?;
-		minimum_seedtime = get_parsed_extension_value(torznab, "minimumseedtime")?.map(Duration::from_secs);
+/// Unparsable dates are dropped with a warning, in any mode.
+fn parse_published_at(item: &FeedItem, ctx: &mut Context<'_>, usenetdate: Option<DateTime<FixedOffset>>) -> Option<DateTime<FixedOffset>> {
+	match item.pub_date.as_deref() {
+		Some(date) => match parse_date(date) {
+			Ok(date) => Some(date),
+			Err(e) => ctx.optional("pubDate", Issue::invalid(date, e), |_| usenetdate.map(|date| (date, "`usenetdate` attr")))
+		},
+		None => usenetdate
+	}
+}
+
+fn parse_link(item: &FeedItem, ctx: &mut Context<'_>) -> Result<String, Error> {
+	if let Some(link) = &item.link {
+		return Ok(link.clone());
+	}
+	let link = ctx.recover("link", Issue::missing(Error::MissingLink), |ctx| {
+		if let Some(magnet) = ctx.attr(Attr::MagnetUrl) {
+			return Some((magnet.to_string(), "`magneturl` attr"));
+		}
+		if let Some(enclosure) = item.enclosure.as_ref().filter(|enclosure| !enclosure.url.is_empty()) {
+			return Some((enclosure.url.clone(), "enclosure URL"));
+		}
+		item.guid.as_deref()
+			.filter(|guid| ["http://", "https://", "magnet:"].iter().any(|scheme| guid.starts_with(scheme)))
+			.map(|guid| (guid.to_string(), "guid"))
+	})?;
+	link.ok_or_else(|| ctx.error("link", None, Error::MissingLink))
+}
+
+fn detect_protocol(item: &FeedItem, ctx: &Context<'_>, default: Protocol) -> Protocol {
+	match item.enclosure.as_ref().map(|enclosure| enclosure.mime_type.as_str()) {
+		Some("application/x-nzb") => return Protocol::Usenet,
+		Some("application/x-bittorrent") => return Protocol::Torrent,
+		_ => {}
+	}
+	if(item.link.as_deref().map(|link| link.starts_with("magnet:")).unwrap_or(false)) {
+		return Protocol::Torrent;
+	}
+	match (ctx.attrs.has(Namespace::Torznab), ctx.attrs.has(Namespace::Newznab)) {
+		(false, true) => Protocol::Usenet,
+		(true, false) => Protocol::Torrent,
+		_ => default
+	}
+}
+
+fn parse_usenet(ctx: &mut Context<'_>, posted_at: Option<DateTime<FixedOffset>>) -> Usenet {
+	let poster = ctx.attr(Attr::Poster).map(str::to_string);
+	let groups = ctx.attrs(Attr::Group).map(str::to_string).collect();
+	let password = match ctx.attr(Attr::Password) {
+		Some(value) => match value.trim() {
+			"0" => Some(PasswordStatus::None),
+			"1" => Some(PasswordStatus::Passworded),
+			"2" => Some(PasswordStatus::InnerArchive),
+			_ => ctx.optional("password", Issue::invalid(value, Error::InvalidValue("password")), |_| None)
+		},
+		None => None
+	};
+	Usenet{poster, groups, posted_at, password}
+}
+
+fn parse_indexer(indexers: &mut [Option<IndexerSource>; 2], ctx: &Context<'_>) -> Option<IndexerSource> {
+	for source in indexers.iter_mut() {
+		if let Some(source) = source.take().filter(|source| source.id.is_some() || source.name.is_some()) {
+			return Some(source);
+		}
 	}
+	ctx.attr(Attr::Indexer).map(|name| IndexerSource{id: None, name: Some(name.to_string())})
+}
+
+pub fn from_item(mut item: FeedItem, options: ParseOptions) -> Result<SearchResult, Error> {
+	let guid = item.guid.as_deref();
+	let mut ctx = Context{
+		guid,
+		mode: options.mode,
+		attrs: &item.attrs,
+		warnings: Vec::new()
+	};
 
-	#[cfg(feature = "require-parse-names")]
-	return Ok(Torrent::new(name, size, categories, link, seeders, leechers, minimum_ratio, minimum_seedtime)?);
-	#[cfg(not(feature = "require-parse-names"))]
-	Ok(Torrent::new(name, size, categories, link, seeders, leechers, minimum_ratio, minimum_seedtime))
+	let name = item.title.take().ok_or_else(|| ctx.error("title", None, Error::MissingTitle))?;
+	let repaired = if(options.repair_titles) { charset::repair_double_encoding(&name) } else { None };
+	let name = match repaired {
+		Some(repaired) => {
+			ctx.warn("title", Some(&name), WarningKind::Repaired, None);
+			repaired
+		},
+		None => name
+	};
+	let size = parse_size(&item, &mut ctx)?;
+	let categories = parse_categories(&item, &mut ctx)?;
+	let link = parse_link(&item, &mut ctx)?;
+	let seeders = ctx.count_attr(Attr::Seeders)?;
+	let peers = ctx.count_attr(Attr::Peers)?;
+	let leechers = match ctx.count_attr(Attr::Leechers)? {
+		Some(leechers) => Some(leechers),
+		None => seeders.zip(peers).map(|(seeders, peers)| peers.saturating_sub(seeders))
+	};
+	let peers = peers.or_else(|| seeders.zip(leechers).map(|(seeders, leechers)| seeders.saturating_add(leechers)));
+	let minimum_ratio = ctx.factor_attr(Attr::MinimumRatio);
+	let minimum_seedtime = ctx.optional_attr(Attr::MinimumSeedtime).map(Duration::from_secs);
+	let download_volume_factor = ctx.factor_attr(Attr::DownloadVolumeFactor);
+	let upload_volume_factor = ctx.factor_attr(Attr::UploadVolumeFactor);
+	let usenetdate = parse_usenetdate(&mut ctx);
+	let published_at = parse_published_at(&item, &mut ctx, usenetdate);
+	let grabs = ctx.optional_attr::<i64>(Attr::Grabs).and_then(|grabs| u32::try_from(grabs).ok());
+	let indexer = parse_indexer(&mut item.indexers, &ctx);
+	let protocol = detect_protocol(&item, &ctx, options.protocol);
+	let usenet = match protocol {
+		Protocol::Usenet => Some(parse_usenet(&mut ctx, usenetdate)),
+		Protocol::Torrent => None
+	};
+
+	Ok(SearchResult{
+		protocol,
+		guid: guid.map(str::to_string),
+		#[cfg(all(feature = "parse-names", not(feature = "require-parse-names")))]
+		metadata: Metadata::from(&name).ok(),
+		#[cfg(feature = "require-parse-names")]
+		metadata: Metadata::from(&name).map_err(|e| ctx.error("title", Some(&name), e.into()))?,
+		name,
+		size,
+		categories,
+		link,
+		seeders,
+		leechers,
+		peers,
+		minimum_ratio,
+		minimum_seedtime,
+		download_volume_factor,
+		upload_volume_factor,
+		published_at,
+		grabs,
+		indexer,
+		usenet,
+		warnings: ctx.warnings
+	})
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::feed::Enclosure;
+
+	fn item(mime_type: &str, link: &str) -> FeedItem {
+		FeedItem{
+			title: Some("t".to_string()),
+			link: Some(link.to_string()),
+			enclosure: Some(Enclosure{url: link.to_string(), length: "1".to_string(), mime_type: mime_type.to_string()}),
+			..FeedItem::default()
+		}
+	}
+
+	#[test]
+	fn protocol() {
+		let protocol = |item: FeedItem, default| from_item(item, ParseOptions{protocol: default, ..ParseOptions::default()}).unwrap().protocol;
+		assert_eq!(protocol(item("application/x-bittorrent", "http://example.com/1"), Protocol::Usenet), Protocol::Torrent);
+		assert_eq!(protocol(item("application/x-nzb", "http://example.com/1"), Protocol::Torrent), Protocol::Usenet);
+		assert_eq!(protocol(item("", "magnet:?xt=urn:btih:00"), Protocol::Usenet), Protocol::Torrent);
+		let mut newznab = item("", "http://example.com/1");
+		newznab.attrs.touch(Namespace::Newznab);
+		assert_eq!(protocol(newznab.clone(), Protocol::Torrent), Protocol::Usenet);
+		newznab.attrs.touch(Namespace::Torznab);
+		assert_eq!(protocol(newznab, Protocol::Usenet), Protocol::Usenet);
+		let result = from_item(item("application/x-bittorrent", "http://example.com/1"), ParseOptions::default()).unwrap();
+		assert!(result.usenet.is_none());
+	}
+
+	#[test]
+	fn indexer_attribution() {
+		let jackett = IndexerSource{id: Some("rarbg".to_string()), name: Some("RARBG".to_string())};
+		let prowlarr = IndexerSource{id: Some("5".to_string()), name: Some("NZBgeek".to_string())};
+		let indexer = |indexers: [Option<IndexerSource>; 2], attr: Option<&str>| {
+			let mut item = FeedItem{indexers, ..item("application/x-bittorrent", "http://example.com/1")};
+			if let Some(name) = attr {
+				item.attrs.push(Namespace::Torznab, Attr::Indexer, name.to_string());
+			}
+			from_item(item, ParseOptions::default()).unwrap().indexer
+		};
+		assert_eq!(indexer([Some(jackett.clone()), Some(prowlarr.clone())], Some("attr")), Some(jackett));
+		assert_eq!(indexer([Some(IndexerSource::default()), Some(prowlarr.clone())], None), Some(prowlarr));
+		assert_eq!(indexer([None, None], Some("attr")), Some(IndexerSource{id: None, name: Some("attr".to_string())}));
+		assert_eq!(indexer([None, None], None), None);
+	}
+}
@@ -0,0 +1,543 @@
+//! The HTTP layer [`Client`](crate::Client), [`Jackett`](crate::Jackett) and [`Prowlarr`](crate::Prowlarr) send
+//! requests through.  [`ReqwestTransport`], behind the default `reqwest` feature, is used unless another
+//! [`Transport`] is given; [`from_fn()`] turns a closure into one, e.g. to serve canned responses in tests.
+//! `UnixTransport`, behind the `unix-socket` feature, reaches indexers listening on a Unix domain socket.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use bytes::Bytes;
+use chrono::DateTime;
+use chrono::Utc;
+use url::Url;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Clone, PartialEq, Eq)]
+#[non_exhaustive]
+/// A GET request; Torznab and the APIs around it need nothing else.  Header values and the `apikey` parameter are
+/// left out of its `Debug` output, since they may be credentials.
+pub struct Request {
+	pub url: Url,
+	pub headers: Vec<(String, String)>,
+	/// Transports should stop reading a body longer than this, failing with [`TransportErrorKind::BodyTooLarge`].
+	pub max_body_size: Option<usize>
+}
+
+impl Request {
+	pub fn get(url: Url) -> Self {
+		Self{url, headers: Vec::new(), max_body_size: None}
+	}
+
+	pub fn header(mut self, name: impl ToString, value: impl ToString) -> Self {
+		self.headers.push((name.to_string(), value.to_string()));
+		self
+	}
+
+	pub fn max_body_size(mut self, limit: usize) -> Self {
+		self.max_body_size = Some(limit);
+		self
+	}
+}
+
+impl fmt::Debug for Request {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut url = self.url.clone();
+		if(url.query_pairs().any(|(k, _)| k == "apikey")) {
+			let query: Vec<(String, String)> = self.url.query_pairs()
+				.map(|(k, v)| (k.to_string(), if(k == "apikey") { "<redacted>".to_string() } else { v.into_owned() }))
+				.collect();
+			url.query_pairs_mut().clear().extend_pairs(query);
+		}
+		if(url.password().is_some()) {
+			let _ = url.set_password(Some("<redacted>"));
+		}
+		f.debug_struct("Request")
+			.field("url", &url.as_str())
+			.field("headers", &self.headers.iter().map(|(name, _)| (name, "<redacted>")).collect::<Vec<_>>())
+			.field("max_body_size", &self.max_body_size)
+			.finish()
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Response {
+	pub status: u16,
+	/// Header names are compared case-insensitively by [`header()`](Self::header).
+	pub headers: Vec<(String, String)>,
+	pub body: Bytes
+}
+
+impl Response {
+	pub fn new(status: u16, body: impl Into<Bytes>) -> Self {
+		Self{status, headers: Vec::new(), body: body.into()}
+	}
+
+	pub fn with_header(mut self, name: impl ToString, value: impl ToString) -> Self {
+		self.headers.push((name.to_string(), value.to_string()));
+		self
+	}
+
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+	}
+
+	/// How long the server asked clients to wait, from a `Retry-After` header in seconds or as an HTTP date.
+	pub fn retry_after(&self) -> Option<Duration> {
+		let value = self.header("Retry-After")?.trim();
+		match value.parse() {
+			Ok(secs) => Some(Duration::from_secs(secs)),
+			Err(_) => Some((DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default())
+		}
+	}
+
+	pub fn is_success(&self) -> bool {
+		(200..300).contains(&self.status)
+	}
+
+	pub fn is_redirection(&self) -> bool {
+		(300..400).contains(&self.status)
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransportErrorKind {
+	/// The server couldn't be reached.
+	Connect,
+	Timeout,
+	/// The response couldn't be read or decoded.
+	Body,
+	/// The response body is longer than [`Request::max_body_size`].
+	BodyTooLarge,
+	Other
+}
+
+#[derive(Debug)]
+/// A request that failed before a response was received.  Implementations must keep URLs out of the error and its
+/// sources, since they carry API keys.
+pub struct TransportError {
+	pub kind: TransportErrorKind,
+	source: Option<Box<dyn std::error::Error + Send + Sync>>
+}
+
+impl TransportError {
+	pub fn new(kind: TransportErrorKind, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+		Self{kind, source: Some(source.into())}
+	}
+}
+
+impl From<TransportErrorKind> for TransportError {
+	fn from(kind: TransportErrorKind) -> Self {
+		Self{kind, source: None}
+	}
+}
+
+impl fmt::Display for TransportError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self.kind {
+			TransportErrorKind::Connect => "connection failed",
+			TransportErrorKind::Timeout => "request timed out",
+			TransportErrorKind::Body => "failed to read response",
+			TransportErrorKind::BodyTooLarge => "response body too large",
+			TransportErrorKind::Other => "request failed"
+		})
+	}
+}
+
+impl std::error::Error for TransportError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		self.source.as_ref().map(|source| source.as_ref() as _)
+	}
+}
+
+/// Sends requests and returns complete responses, whatever their status.  Redirects should be followed, except to
+/// `magnet:` URIs, which are returned as-is so that [`Client::download()`](crate::Client::download) can pick them up.
+/// A redirect to another origin must be followed without the request's headers, which may carry credentials.
+pub trait Transport: Send + Sync {
+	fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, TransportError>>;
+}
+
+/// Sends `request`, enforcing its [`max_body_size`](Request::max_body_size) whether or not `transport` does.
+pub(crate) async fn send(transport: &dyn Transport, request: Request) -> Result<Response, crate::Error> {
+	let limit = request.max_body_size;
+	let response = match transport.send(request).await {
+		Err(TransportError{kind: TransportErrorKind::BodyTooLarge, ..}) => return Err(crate::Error::BodyTooLarge{limit: limit.unwrap_or_default()}),
+		response => response?
+	};
+	if let Some(limit) = limit.filter(|limit| response.body.len() > *limit) {
+		return Err(crate::Error::BodyTooLarge{limit});
+	}
+	Ok(response)
+}
+
+/// Fails unless `response` is successful.
+pub(crate) fn success(response: Response) -> Result<Response, crate::Error> {
+	if(!response.is_success()) {
+		return Err(crate::Error::Status(response.status));
+	}
+	Ok(response)
+}
+
+pub struct FnTransport<F>(F);
+
+impl<F> Transport for FnTransport<F> where F: Fn(Request) -> Result<Response, TransportError> + Send + Sync {
+	fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, TransportError>> {
+		let response = (self.0)(request);
+		Box::pin(async move { response })
+	}
+}
+
+/// A [`Transport`] answering each request synchronously with `f`.
+pub fn from_fn<F>(f: F) -> FnTransport<F> where F: Fn(Request) -> Result<Response, TransportError> + Send + Sync {
+	FnTransport(f)
+}
+
+#[cfg(feature = "reqwest")]
+pub use self::reqwest_transport::{ReqwestTransport, ReqwestTransportBuilder};
+#[cfg(all(unix, feature = "unix-socket"))]
+pub use crate::unix::UnixTransport;
+
+#[cfg(feature = "reqwest")]
+mod reqwest_transport {
+	use reqwest::redirect;
+
+	use super::*;
+
+	#[derive(Clone, Debug)]
+	/// A [`Transport`] backed by [`reqwest`].
+	pub struct ReqwestTransport {
+		client: reqwest::Client,
+		/// A client that doesn't check certificates, and the hosts requests to which are sent through it, for TLS
+		/// backends that can't exempt hosts themselves.
+		insecure: Option<(Vec<String>, reqwest::Client)>
+	}
+
+	impl ReqwestTransport {
+		/// Decompresses gzip, and brotli, deflate and zstd where their features are enabled, and stops redirects at
+		/// magnet URIs.
+		pub fn new() -> Result<Self, TransportError> {
+			Self::builder().build()
+		}
+
+		/// Like [`new()`](Self::new), with TLS options.  These are set per transport, so to set them for a single
+		/// [`Client`](crate::Client), create it with [`Client::with_transport()`](crate::Client::with_transport).
+		pub fn builder() -> ReqwestTransportBuilder {
+			ReqwestTransportBuilder::default()
+		}
+	}
+
+	#[derive(Clone, Default)]
+	pub struct ReqwestTransportBuilder {
+		#[cfg(any(feature = "rustls", feature = "native-tls"))]
+		tls: crate::tls::TlsOptions
+	}
+
+	#[cfg(any(feature = "rustls", feature = "native-tls"))]
+	impl ReqwestTransportBuilder {
+		/// Presents a client certificate, for indexers behind mutual TLS.  `pem` holds the private key and the
+		/// certificate chain.  With rustls, the key may be PKCS#8, RSA or SEC1; native-tls needs PKCS#8.
+		pub fn client_identity(mut self, pem: impl Into<Vec<u8>>) -> Self {
+			self.tls.identity = Some(pem.into());
+			self
+		}
+
+		/// Trusts the certificates in `pem`, e.g. an internal CA's, as well as the usual roots.
+		pub fn root_certificates(mut self, pem: impl Into<Vec<u8>>) -> Self {
+			self.tls.roots.push(pem.into());
+			self
+		}
+
+		/// Accepts `host`'s certificate if, and only if, its SHA-256 fingerprint is `fingerprint`, written in hex with
+		/// or without colons.  The certificate needn't chain to a trusted root, so this suits self-signed indexers.
+		/// Pins may be given more than once per host, e.g. across a certificate renewal.  Needs the `rustls` feature;
+		/// [`build()`](Self::build) fails otherwise.
+		pub fn pin_certificate(mut self, host: impl ToString, fingerprint: impl ToString) -> Self {
+			self.tls.pins.push((host.to_string(), fingerprint.to_string()));
+			self
+		}
+
+		/// Accepts any certificate from `host`, however invalid.  As the name says, this is dangerous:  anyone able to
+		/// intercept traffic to `host` can read it, credentials included.  Prefer
+		/// [`root_certificates()`](Self::root_certificates) or [`pin_certificate()`](Self::pin_certificate).
+		pub fn danger_accept_invalid_certs_for(mut self, host: impl ToString) -> Self {
+			self.tls.insecure_hosts.push(host.to_string());
+			self
+		}
+	}
+
+	impl ReqwestTransportBuilder {
+		pub fn build(self) -> Result<ReqwestTransport, TransportError> {
+			let builder = || {
+				let builder = reqwest::Client::builder()
+					.gzip(true)
+					.redirect(redirect_policy());
+				#[cfg(feature = "brotli")]
+				let builder = builder.brotli(true);
+				#[cfg(feature = "deflate")]
+				let builder = builder.deflate(true);
+				builder
+			};
+			#[cfg(any(feature = "rustls", feature = "native-tls"))]
+			{
+				let insecure = match crate::tls::insecure(builder(), &self.tls)? {
+					Some(insecure) => Some((self.tls.insecure_hosts.clone(), insecure.build()?)),
+					None => None
+				};
+				Ok(ReqwestTransport{client: crate::tls::configure(builder(), &self.tls)?.build()?, insecure})
+			}
+			#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+			Ok(ReqwestTransport{client: builder().build()?, insecure: None})
+		}
+	}
+
+	impl fmt::Debug for ReqwestTransportBuilder {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			let mut debug = f.debug_struct("ReqwestTransportBuilder");
+			#[cfg(any(feature = "rustls", feature = "native-tls"))]
+			debug
+				.field("identity", &self.tls.identity.as_ref().map(|_| "<redacted>"))
+				.field("roots", &self.tls.roots.len())
+				.field("pins", &self.tls.pins)
+				.field("insecure_hosts", &self.tls.insecure_hosts);
+			debug.finish()
+		}
+	}
+
+	/// Wraps a preconfigured client.  Its redirect policy should stop at magnet URIs and at redirects to another
+	/// origin, as [`ReqwestTransport::new()`]'s does.
+	impl From<reqwest::Client> for ReqwestTransport {
+		fn from(client: reqwest::Client) -> Self {
+			Self{client, insecure: None}
+		}
+	}
+
+	/// Follows redirects as reqwest normally would, except to magnet URIs, which indexers use in place of `.torrent`
+	/// files and which can't be fetched, and to other origins, which `send()` follows itself so as
+	/// to drop every header the request carried.  reqwest would only drop `Authorization` and `Cookie`.
+	fn redirect_policy() -> redirect::Policy {
+		redirect::Policy::custom(|attempt| {
+			let cross_origin = attempt.previous().last().map(|previous| previous.origin() != attempt.url().origin()).unwrap_or(false);
+			if(attempt.url().scheme() == "magnet" || cross_origin) {
+				attempt.stop()
+			} else if(attempt.previous().len() >= 10) {
+				attempt.error("too many redirects")
+			} else {
+				attempt.follow()
+			}
+		})
+	}
+
+	impl From<reqwest::Error> for TransportError {
+		fn from(err: reqwest::Error) -> Self {
+			let kind = if(err.is_connect()) {
+				TransportErrorKind::Connect
+			} else if(err.is_timeout()) {
+				TransportErrorKind::Timeout
+			} else if(err.is_body() || err.is_decode()) {
+				TransportErrorKind::Body
+			} else {
+				TransportErrorKind::Other
+			};
+			Self::new(kind, err.without_url())
+		}
+	}
+
+	/// reqwest 0.11 can't decode zstd, so when it's enabled the `Accept-Encoding` header is sent explicitly, listing
+	/// the encodings reqwest decodes along with zstd, which is then decoded here.
+	#[cfg(feature = "zstd")]
+	fn accept_encoding() -> String {
+		let mut encodings = vec!["gzip"];
+		if(cfg!(feature = "brotli")) {
+			encodings.push("br");
+		}
+		if(cfg!(feature = "deflate")) {
+			encodings.push("deflate");
+		}
+		encodings.push("zstd");
+		encodings.join(", ")
+	}
+
+	#[cfg(feature = "zstd")]
+	pub(super) fn decode_zstd(body: &[u8], limit: usize) -> Result<Vec<u8>, TransportError> {
+		use std::io::Read;
+
+		let decoder = zstd::stream::read::Decoder::new(body).map_err(|e| TransportError::new(TransportErrorKind::Body, e))?;
+		let mut decoded = Vec::new();
+		decoder.take((limit as u64).saturating_add(1)).read_to_end(&mut decoded).map_err(|e| TransportError::new(TransportErrorKind::Body, e))?;
+		if(decoded.len() > limit) {
+			return Err(TransportErrorKind::BodyTooLarge.into());
+		}
+		Ok(decoded)
+	}
+
+	impl ReqwestTransport {
+		/// Sends `request`, following redirects within its origin; returns the URL the response came from.
+		async fn send_once(&self, request: &Request) -> Result<(Url, Response), TransportError> {
+			let limit = request.max_body_size.unwrap_or(usize::MAX);
+			let client = match &self.insecure {
+				Some((hosts, insecure)) if(hosts.iter().any(|host| request.url.host_str().map(|h| h.eq_ignore_ascii_case(host)).unwrap_or(false))) => insecure,
+				_ => &self.client
+			};
+			let mut builder = client.get(request.url.clone());
+			for (name, value) in &request.headers {
+				builder = builder.header(name.as_str(), value.as_str());
+			}
+			#[cfg(feature = "zstd")]
+			if(!request.headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Accept-Encoding"))) {
+				builder = builder.header("Accept-Encoding", accept_encoding());
+			}
+			let mut response = builder.send().await?;
+			let url = response.url().clone();
+			if(response.content_length().map(|length| length > limit as u64).unwrap_or(false)) {
+				return Err(TransportErrorKind::BodyTooLarge.into());
+			}
+			let status = response.status().as_u16();
+			let headers: Vec<(String, String)> = response.headers().iter()
+				.filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+				.collect();
+			// Read chunk by chunk rather than with `bytes()`, so that an oversized body is dropped as soon as it's
+			// known to be, including one that's only oversized once decompressed.
+			let mut body = Vec::new();
+			while let Some(chunk) = response.chunk().await? {
+				if(chunk.len() > limit - body.len()) {
+					return Err(TransportErrorKind::BodyTooLarge.into());
+				}
+				body.extend_from_slice(&chunk);
+			}
+			#[cfg(feature = "zstd")]
+			let mut headers = headers;
+			#[cfg(feature = "zstd")]
+			if(headers.iter().any(|(name, value)| name.eq_ignore_ascii_case("Content-Encoding") && value.trim().eq_ignore_ascii_case("zstd"))) {
+				body = decode_zstd(&body, limit)?;
+				// As reqwest does for the encodings it decodes.
+				headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Content-Encoding") && !name.eq_ignore_ascii_case("Content-Length"));
+			}
+			Ok((url, Response{status, headers, body: body.into()}))
+		}
+	}
+
+	impl Transport for ReqwestTransport {
+		fn send(&self, mut request: Request) -> BoxFuture<'_, Result<Response, TransportError>> {
+			Box::pin(async move {
+				for _ in 0..10 {
+					let (url, response) = self.send_once(&request).await?;
+					let location = match response.header("Location") {
+						Some(location) if(response.is_redirection()) => location,
+						_ => return Ok(response)
+					};
+					// The redirect policy only stops at magnet URIs and other origins.
+					match url.join(location) {
+						Ok(next) if(next.scheme() != "magnet" && next.origin() != url.origin()) => {
+							request.headers.clear();
+							request.url = next;
+						},
+						_ => return Ok(response)
+					}
+				}
+				Err(TransportError::new(TransportErrorKind::Other, "too many redirects"))
+			})
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Client;
+	use crate::Error;
+	use crate::TorznabCategory;
+
+	#[tokio::test]
+	async fn in_memory() {
+		let client = Client::with_transport("http://indexer.invalid/api", "key", from_fn(|request| {
+			assert_eq!(request.url.query_pairs().find(|(k, _)| k == "apikey").unwrap().1, "key");
+			match request.url.query_pairs().find(|(k, _)| k == "t").map(|(_, t)| t.into_owned()).as_deref() {
+				Some("caps") => Ok(Response::new(503, "")),
+				_ => Err(TransportErrorKind::Connect.into())
+			}
+		})).unwrap();
+		assert!(matches!(client.caps().await, Err(Error::Status(503))));
+		assert!(matches!(client.get(TorznabCategory::Movies2000, vec![]).await, Err(Error::Transport(TransportError{kind: TransportErrorKind::Connect, ..}))));
+	}
+
+	#[test]
+	fn retry_after() {
+		assert_eq!(Response::new(429, "").with_header("retry-after", "120").retry_after(), Some(Duration::from_secs(120)));
+		assert_eq!(Response::new(429, "").with_header("Retry-After", "Wed, 21 Oct 2015 07:28:00 GMT").retry_after(), Some(Duration::ZERO));
+		let later = (Utc::now() + chrono::Duration::hours(1)).to_rfc2822();
+		assert!(Response::new(503, "").with_header("Retry-After", later).retry_after().unwrap() > Duration::from_secs(3590));
+		assert_eq!(Response::new(429, "").with_header("Retry-After", "soon").retry_after(), None);
+		assert_eq!(Response::new(429, "").retry_after(), None);
+	}
+
+	#[tokio::test]
+	async fn body_limit() {
+		let limits = crate::Limits{max_body_size: 16, ..Default::default()};
+		let client = Client::with_transport("http://indexer.invalid/api", "key", from_fn(|request| {
+			assert_eq!(request.max_body_size, Some(16));
+			Ok(Response::new(200, vec![b' '; 17]))
+		})).unwrap().with_limits(limits);
+		assert!(matches!(client.caps().await, Err(Error::BodyTooLarge{limit: 16})));
+
+		#[cfg(feature = "reqwest")]
+		{
+			use crate::test_server;
+
+			let base_url = test_server::serve(|_| test_server::Response::new(200, "x".repeat(17))).await;
+			let client = Client::new(base_url, "key").unwrap().with_limits(limits);
+			assert!(matches!(client.caps().await, Err(Error::BodyTooLarge{limit: 16})));
+		}
+	}
+
+	#[cfg(all(feature = "reqwest", any(feature = "rustls", feature = "native-tls")))]
+	#[test]
+	fn client_identity() {
+		let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+		let pem = format!("{}{}", cert.serialize_private_key_pem(), cert.serialize_pem().unwrap());
+		ReqwestTransport::builder().client_identity(pem).build().unwrap();
+		assert!(ReqwestTransport::builder().client_identity("not a certificate").build().is_err());
+	}
+
+	/// Fetches `compressed` from a local server as `encoding`, checking that it was asked for.
+	#[cfg(all(feature = "reqwest", any(feature = "brotli", feature = "deflate", feature = "zstd")))]
+	async fn fetch_encoded(encoding: &'static str, compressed: Vec<u8>) -> Response {
+		use crate::test_server;
+
+		let base_url = test_server::serve(move |request| {
+			let accepted = request.header("Accept-Encoding").unwrap_or_default();
+			assert!(accepted.split(',').any(|e| e.trim() == encoding), "{} not in {:?}", encoding, accepted);
+			test_server::Response::new(200, compressed.clone()).header("Content-Encoding", encoding)
+		}).await;
+		ReqwestTransport::new().unwrap().send(Request::get(base_url.parse().unwrap())).await.unwrap()
+	}
+
+	#[cfg(all(feature = "reqwest", feature = "brotli"))]
+	#[tokio::test]
+	async fn brotli() {
+		let mut compressed = Vec::new();
+		brotli::BrotliCompress(&mut &b"<rss />"[..], &mut compressed, &Default::default()).unwrap();
+		assert_eq!(fetch_encoded("br", compressed).await.body, "<rss />");
+	}
+
+	#[cfg(all(feature = "reqwest", feature = "deflate"))]
+	#[tokio::test]
+	async fn deflate() {
+		use std::io::Write;
+
+		let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+		encoder.write_all(b"<rss />").unwrap();
+		assert_eq!(fetch_encoded("deflate", encoder.finish().unwrap()).await.body, "<rss />");
+	}
+
+	#[cfg(all(feature = "reqwest", feature = "zstd"))]
+	#[tokio::test]
+	async fn zstd() {
+		let response = fetch_encoded("zstd", zstd::encode_all(&b"<rss />"[..], 0).unwrap()).await;
+		assert_eq!(response.body, "<rss />");
+		assert!(response.header("Content-Encoding").is_none());
+
+		assert!(matches!(reqwest_transport::decode_zstd(&zstd::encode_all(&[b' '; 17][..], 0).unwrap(), 16), Err(TransportError{kind: TransportErrorKind::BodyTooLarge, ..})));
+	}
+}
@@ -0,0 +1,185 @@
+//! [`UnixTransport`], for indexers listening on a Unix domain socket rather than a TCP port.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use hyper::body::HttpBody;
+use hyper::Body;
+use tokio::net::UnixStream;
+use url::Url;
+
+use crate::transport::BoxFuture;
+use crate::transport::Request;
+use crate::transport::Response;
+use crate::transport::Transport;
+use crate::transport::TransportError;
+use crate::transport::TransportErrorKind;
+
+/// Parses a base URL of the form `unix:{socket}:{path}`, as nginx writes them, e.g.
+/// `unix:/run/jackett/jackett.sock:/api/v2.0`, into the socket's path and an equivalent URL for the host `localhost`.
+/// Returns `None` for URLs of other schemes.
+#[cfg(feature = "reqwest")]
+pub(crate) fn parse_url(base_url: &str) -> Option<Result<(PathBuf, String), crate::Error>> {
+	let rest = base_url.strip_prefix("unix:")?;
+	let (socket, path) = rest.split_once(':').unwrap_or((rest, ""));
+	if(socket.is_empty() || !(path.is_empty() || path.starts_with('/'))) {
+		return Some(Err(crate::Error::InvalidValue("base_url")));
+	}
+	Some(Ok((socket.into(), format!("http://localhost{}", path))))
+}
+
+/// Sends requests for one host over a Unix socket, speaking HTTP/1.1 with a fresh connection per request.  Requests for
+/// other hosts, e.g. for downloads from a tracker, go to a fallback transport if there is one, and fail otherwise.
+/// Responses aren't compressed, since the socket is local.
+#[derive(Clone)]
+pub struct UnixTransport {
+	socket: PathBuf,
+	host: String,
+	fallback: Option<Arc<dyn Transport>>
+}
+
+impl UnixTransport {
+	/// Sends requests for `host`, which is also sent as the `Host` header, to the socket at `socket`.
+	pub fn new(socket: impl Into<PathBuf>, host: impl ToString) -> Self {
+		Self{socket: socket.into(), host: host.to_string(), fallback: None}
+	}
+
+	/// Sends requests for other hosts through `fallback`.
+	pub fn with_fallback(mut self, fallback: impl Transport + 'static) -> Self {
+		self.fallback = Some(Arc::new(fallback));
+		self
+	}
+
+	#[cfg(feature = "reqwest")]
+	pub(crate) fn with_fallback_arc(mut self, fallback: Arc<dyn Transport>) -> Self {
+		self.fallback = Some(fallback);
+		self
+	}
+
+	fn is_local(&self, url: &Url) -> bool {
+		url.scheme() == "http" && url.host_str().map(|host| host.eq_ignore_ascii_case(&self.host)).unwrap_or(false)
+	}
+
+	async fn send_local(&self, request: &Request) -> Result<Response, TransportError> {
+		let stream = UnixStream::connect(&self.socket).await.map_err(|e| TransportError::new(TransportErrorKind::Connect, e))?;
+		let (mut sender, connection) = hyper::client::conn::handshake(stream).await.map_err(|e| TransportError::new(TransportErrorKind::Connect, e))?;
+		tokio::spawn(async move {
+			let _ = connection.await;
+		});
+		let target = match request.url.query() {
+			Some(query) => format!("{}?{}", request.url.path(), query),
+			None => request.url.path().to_string()
+		};
+		let mut builder = hyper::Request::get(target).header("Host", &self.host);
+		for (name, value) in &request.headers {
+			builder = builder.header(name.as_str(), value.as_str());
+		}
+		let http_request = builder.body(Body::empty()).map_err(|e| TransportError::new(TransportErrorKind::Other, e))?;
+		let response = sender.send_request(http_request).await.map_err(|e| TransportError::new(TransportErrorKind::Other, e))?;
+		let limit = request.max_body_size.unwrap_or(usize::MAX);
+		let status = response.status().as_u16();
+		let headers: Vec<(String, String)> = response.headers().iter()
+			.filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+			.collect();
+		let mut body = response.into_body();
+		if(body.size_hint().lower() > limit as u64) {
+			return Err(TransportErrorKind::BodyTooLarge.into());
+		}
+		let mut bytes = Vec::new();
+		while let Some(chunk) = body.data().await {
+			let chunk = chunk.map_err(|e| TransportError::new(TransportErrorKind::Body, e))?;
+			if(chunk.len() > limit - bytes.len()) {
+				return Err(TransportErrorKind::BodyTooLarge.into());
+			}
+			bytes.extend_from_slice(&chunk);
+		}
+		Ok(Response{status, headers, body: bytes.into()})
+	}
+}
+
+impl std::fmt::Debug for UnixTransport {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("UnixTransport")
+			.field("socket", &self.socket)
+			.field("host", &self.host)
+			.field("fallback", &self.fallback.is_some())
+			.finish()
+	}
+}
+
+impl Transport for UnixTransport {
+	fn send(&self, mut request: Request) -> BoxFuture<'_, Result<Response, TransportError>> {
+		Box::pin(async move {
+			for _ in 0..10 {
+				if(!self.is_local(&request.url)) {
+					return match &self.fallback {
+						Some(fallback) => fallback.send(request).await,
+						None => Err(TransportErrorKind::Connect.into())
+					};
+				}
+				let response = self.send_local(&request).await?;
+				let location = match response.header("Location") {
+					Some(location) if(response.is_redirection()) => location,
+					_ => return Ok(response)
+				};
+				let url = match request.url.join(location) {
+					Ok(url) if(url.scheme() != "magnet") => url,
+					_ => return Ok(response)
+				};
+				// Headers may carry credentials, which mustn't follow a redirect elsewhere.
+				if(!self.is_local(&url)) {
+					request.headers.clear();
+				}
+				request.url = url;
+			}
+			Err(TransportError::new(TransportErrorKind::Other, "too many redirects"))
+		})
+	}
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+	use super::*;
+	use crate::test_server;
+	use crate::transport::from_fn;
+	use crate::Client;
+
+	#[test]
+	fn urls() {
+		let (socket, url) = parse_url("unix:/run/jackett/jackett.sock:/api/v2.0").unwrap().unwrap();
+		assert_eq!((socket, url.as_str()), (PathBuf::from("/run/jackett/jackett.sock"), "http://localhost/api/v2.0"));
+		assert_eq!(parse_url("unix:/run/jackett.sock").unwrap().unwrap().1, "http://localhost");
+		assert!(parse_url("unix::/api").unwrap().is_err());
+		assert!(parse_url("http://localhost/api").is_none());
+	}
+
+	#[tokio::test]
+	async fn over_socket() {
+		let socket = test_server::serve_unix(|request| match request.path() {
+			"/api" => {
+				assert_eq!(request.query("apikey").as_deref(), Some("key"));
+				test_server::Response::new(200, "<caps />")
+			},
+			"/echo" => test_server::Response::new(200, format!("{} {}", request.header("Host").unwrap_or_default(), request.query("q").unwrap_or_default())),
+			"/moved" => test_server::Response::new(302, "").header("Location", "/echo?q=moved"),
+			"/magnet" => test_server::Response::new(302, "").header("Location", "magnet:?xt=urn:btih:0"),
+			"/elsewhere" => test_server::Response::new(302, "").header("Location", "http://tracker.example/1.torrent"),
+			_ => test_server::Response::new(404, "")
+		}).await;
+
+		Client::new(format!("unix:{}:/api", socket.display()), "key").unwrap().caps().await.unwrap();
+		Client::with_unix_socket(&socket, "http://jackett/", "key").unwrap().caps().await.unwrap();
+
+		let transport = UnixTransport::new(&socket, "jackett").with_fallback(from_fn(|request| {
+			assert!(request.headers.is_empty());
+			Ok(Response::new(200, request.url.to_string()))
+		}));
+		let get = |url: &str| transport.send(Request::get(url.parse().unwrap()).header("X-Api-Key", "key"));
+		assert_eq!(get("http://jackett/echo?q=direct").await.unwrap().body, "jackett direct");
+		assert_eq!(get("http://jackett/moved").await.unwrap().body, "jackett moved");
+		assert_eq!(get("http://jackett/magnet").await.unwrap().header("Location"), Some("magnet:?xt=urn:btih:0"));
+		assert_eq!(get("http://jackett/elsewhere").await.unwrap().body, "http://tracker.example/1.torrent");
+		assert!(UnixTransport::new(&socket, "jackett").send(Request::get("http://tracker.example/".parse().unwrap())).await.is_err());
+		let _ = std::fs::remove_file(socket);
+	}
+}
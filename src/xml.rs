@@ -0,0 +1,65 @@
+//! Helpers shared by the hand-rolled XML parsers.
+
+use std::io::BufRead;
+
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use quick_xml::XmlVersion;
+
+use crate::Error;
+
+/// Value of the attribute whose local name is `key`.
+pub(crate) fn attr<R: BufRead>(reader: &Reader<R>, element: &BytesStart<'_>, key: &[u8]) -> Result<Option<String>, Error> {
+	for attr in element.attributes() {
+		let attr = attr.map_err(quick_xml::Error::from)?;
+		if(attr.key.local_name().as_ref() == key) {
+			return Ok(Some(attr.decoded_and_normalized_value(XmlVersion::Implicit1_0, reader.decoder())?.into_owned()));
+		}
+	}
+	Ok(None)
+}
+
+/// Skips past the end of `element`, including any children.
+pub(crate) fn skip<R: BufRead>(reader: &mut Reader<R>, element: &BytesStart<'_>) -> Result<(), Error> {
+	let end = element.name().as_ref().to_vec();
+	reader.read_to_end_into(quick_xml::name::QName(&end), &mut Vec::new())?;
+	Ok(())
+}
+
+/// Text content of the current element, with children skipped and surrounding whitespace trimmed.
+pub(crate) fn element_text<R: BufRead>(reader: &mut Reader<R>) -> Result<Option<String>, Error> {
+	element_text_into(reader, &mut Vec::new(), usize::MAX)
+}
+
+/// Like [`element_text()`], but reads events into `buf`, so that a caller reading many elements can reuse it, and
+/// fails with [`Error::StringTooLong`] once the text is longer than `max_len`.
+pub(crate) fn element_text_into<R: BufRead>(reader: &mut Reader<R>, buf: &mut Vec<u8>, max_len: usize) -> Result<Option<String>, Error> {
+	let mut text = String::new();
+	buf.clear();
+	loop {
+		match reader.read_event_into(buf)? {
+			Event::Start(element) => skip(reader, &element)?,
+			Event::Text(element) => text.push_str(&element.decode().map_err(quick_xml::Error::from)?),
+			Event::CData(element) => text.push_str(&reader.decoder().decode(&element).map_err(quick_xml::Error::from)?),
+			Event::GeneralRef(entity) => {
+				let name = entity.decode().map_err(quick_xml::Error::from)?;
+				if let Some(resolved) = quick_xml::escape::resolve_predefined_entity(&name) {
+					text.push_str(resolved);
+				} else if let Some(c) = entity.resolve_char_ref()? {
+					text.push(c);
+				}
+			},
+			Event::End(_) | Event::Eof => break,
+			_ => {}
+		}
+		if(text.len() > max_len) {
+			return Err(Error::StringTooLong{limit: max_len});
+		}
+		buf.clear();
+	}
+	text.truncate(text.trim_end().len());
+	let leading = text.len() - text.trim_start().len();
+	text.drain(..leading);
+	Ok(Some(text).filter(|text| !text.is_empty()))
+}